@@ -38,6 +38,21 @@ pub struct LimitsInfoData {
     pub issued: i32,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LicenseViolationData {
+    pub token_id: String,
+    pub sku_id: String,
+    pub limitation: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetChangeReportData {
+    pub violations: Vec<LicenseViolationData>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Context {
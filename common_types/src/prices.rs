@@ -1,11 +1,14 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{ext_contract, Timestamp, AccountId, Promise};
-use crate::utils::is_mainnet;
+use near_sdk::{env, ext_contract, Timestamp, AccountId, Promise};
+use crate::utils::{amount_to_string, is_mainnet, NEAR_DECIMALS};
 
 pub type DurationSec = u32;
 pub type AssetId = String;
 
+// Default recency of the oracle reports, same as the recency_duration_sec of priceoracle.
+pub const DEFAULT_PRICE_MAX_AGE_SEC: DurationSec = 90;
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct PriceData {
@@ -44,17 +47,41 @@ impl Price {
         // price: { multiplier: '13564', decimals: 28 }
         // should be "1.3564"
         // == 13564 / (10**(28-24))
-        return self.float().to_string()
+        let (rate, decimals) = self.rate().unwrap_or_else(|| env::panic_str("Invalid NEAR price"));
+        amount_to_string(rate, decimals)
     }
 
-    pub fn float(&self) -> f64 {
-        // price: { multiplier: '13564', decimals: 28 }
-        // should be "1.3564"
-        // == 13564 / (10**(28-24))
-        let denominator = (10 as f64).powf(self.decimals as f64 - 24.0);
-        let nominator: f64 = self.multiplier.parse().unwrap();
-        return nominator / denominator
+    // USD per NEAR as rate / 10**decimals, the multiplier is per yoctoNEAR
+    pub fn rate(&self) -> Option<(u128, u8)> {
+        let multiplier: u128 = self.multiplier.parse().ok()?;
+        if self.decimals >= NEAR_DECIMALS {
+            // the rate must fit into u128 with its decimals
+            10u128.checked_pow((self.decimals - NEAR_DECIMALS) as u32)?;
+            Some((multiplier, self.decimals - NEAR_DECIMALS))
+        } else {
+            Some((multiplier.checked_mul(10u128.checked_pow((NEAR_DECIMALS - self.decimals) as u32)?)?, 0))
+        }
+    }
+
+    // multiplier for the given (not smaller) decimals
+    fn scaled(&self, decimals: u8) -> Option<u128> {
+        let multiplier: u128 = self.multiplier.parse().ok()?;
+        multiplier.checked_mul(10u128.checked_pow(decimals.checked_sub(self.decimals)? as u32)?)
+    }
+}
+
+// Median of the prices, the mean of the two middle ones for an even count.
+pub fn median_price(prices: &[Price]) -> Option<Price> {
+    let decimals = prices.iter().map(|x| x.decimals).max()?;
+    let mut values: Vec<u128> = prices.iter().map(|x| x.scaled(decimals)).collect::<Option<Vec<u128>>>()?;
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    if values.len() % 2 == 1 {
+        return Some(Price{multiplier: values[middle].to_string(), decimals})
     }
+    // (a + b) / 2 == (a + b) * 5 / 10, exact with one more decimal
+    let sum = values[middle - 1].checked_add(values[middle])?;
+    Some(Price{multiplier: sum.checked_mul(5)?.to_string(), decimals: decimals.checked_add(1)?})
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
@@ -73,6 +100,75 @@ pub struct Report {
     pub price: Price,
 }
 
+// Oracle used to price SKUs in NEAR, None fields mean defaults.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OracleConfig {
+    pub account_id: Option<AccountId>,
+    // reports older than that are ignored
+    pub max_age_sec: Option<DurationSec>,
+}
+
+impl OracleConfig {
+    pub fn oracle_id(&self) -> AccountId {
+        self.account_id.clone().unwrap_or_else(oracle_account)
+    }
+
+    pub fn max_age_sec(&self) -> DurationSec {
+        self.max_age_sec.unwrap_or(DEFAULT_PRICE_MAX_AGE_SEC)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_age_sec == Some(0) {
+            return Err("Oracle max_age_sec must be positive".to_string())
+        }
+        Ok(())
+    }
+}
+
+// Price set by the owner for the time the oracle is down.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FallbackPrice {
+    pub price: Price,
+    // ms
+    pub expires_at: u64,
+}
+
+impl FallbackPrice {
+    // the price must convert to a non-zero rate, otherwise no SKU could be priced with it
+    pub fn validate(&self) -> Result<(), String> {
+        match self.price.rate() {
+            Some((rate, _)) if rate > 0 => Ok(()),
+            _ => Err(format!("Invalid fallback price {} with {} decimals", self.price.multiplier, self.price.decimals)),
+        }
+    }
+}
+
+// NEAR price at now (ms): the median of the fresh oracle reports, the fallback price otherwise.
+pub fn select_near_price(
+    asset: Option<&Asset>, now: u64, max_age_sec: DurationSec, fallback: Option<&FallbackPrice>,
+) -> Result<Price, String> {
+    let max_age_ms = max_age_sec as u64 * 1000;
+    let fresh: Vec<Price> = asset.map(|x| x.reports.iter()
+        // report timestamps are in nanoseconds
+        .filter(|report| report.timestamp.parse::<u64>()
+            .map(|timestamp| now.saturating_sub(timestamp / 1_000_000) <= max_age_ms)
+            .unwrap_or(false))
+        .map(|report| report.price.clone())
+        .collect()
+    ).unwrap_or_default();
+
+    if let Some(price) = median_price(&fresh) {
+        return Ok(price)
+    }
+    match fallback {
+        Some(fallback) if fallback.expires_at > now => Ok(fallback.price.clone()),
+        _ if asset.is_none() => Err("Failed call priceoracle.get_asset".to_string()),
+        _ => Err(format!("NEAR price reports are older than {} seconds", max_age_sec)),
+    }
+}
+
 #[ext_contract(price_oracle_contract)]
 pub trait PriceOracleContract {
     fn get_price_data(&self, asset_ids: Option<Vec<AssetId>>) -> PriceData;
@@ -95,8 +191,8 @@ pub fn wrap_account() -> String {
     }
 }
 
-pub fn get_near_price(gas_weight: u64) -> Promise {
-    let promise = price_oracle_contract::ext(oracle_account())
+pub fn get_near_price(oracle_id: AccountId, gas_weight: u64) -> Promise {
+    let promise = price_oracle_contract::ext(oracle_id)
         .with_unused_gas_weight(gas_weight)
         .get_asset(wrap_account());
     return promise
@@ -1,9 +1,11 @@
+use std::fmt;
 use near_sdk::env;
+use near_sdk::json_types::U128;
 use near_sdk::serde_json;
 use crate::*;
 use crate::policy::{LimitationData, LimitsInfoData, PolicyData};
 use crate::prices::Price;
-use crate::utils::{get_inventory_id, get_objects};
+use crate::utils::{amount_from_string, amount_to_string, get_inventory_id, get_objects, mul_div_round, NEAR_DECIMALS};
 
 pub type TokenId = String;
 pub type AssetId = String;
@@ -22,6 +24,8 @@ pub trait LicenseGeneral {
     fn token_id(&self) -> String;
     fn objects(&self) -> Vec<String>;
     fn object_hash(&self) -> String;
+    fn owner_id(&self) -> Option<AccountId>;
+    fn issued_at(&self) -> Option<u64>;
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -94,6 +98,12 @@ impl TokenMetadata {
             // title: self.title.clone(),
         }
     }
+
+    // End of the refund window of the license, counted from its issue by the SKU it was issued for.
+    pub fn refund_deadline(&self) -> Option<u64> {
+        let days = self.sku_data.as_ref()?.get_params().refund_window_days?;
+        self.issued_at.map(|issued_at| issued_at + days * MS_IN_DAY)
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Default, Clone)]
@@ -187,6 +197,41 @@ pub struct LicenseData {
     pub template: Option<String>,
     pub pdf_url: Option<String>,
     pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub transferability: Option<Transferability>,
+}
+
+// Transfer rule of a license token, free transfer if not set.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum Transferability {
+    #[default]
+    Free,
+    NonTransferable,
+    // fee in yoctoNEAR, paid to receiver_id (licensor by default)
+    WithFee { fee: U128, receiver_id: Option<AccountId> },
+    Allowlisted { account_ids: Vec<AccountId> },
+}
+
+impl Transferability {
+    pub fn check_receiver(&self, receiver_id: &AccountId) -> Result<(), String> {
+        match self {
+            Transferability::NonTransferable => Err("License token is non-transferable".to_string()),
+            Transferability::Allowlisted { account_ids } if !account_ids.contains(receiver_id) => Err(
+                format!("License token can only be transferred to allowlisted accounts, {} is not allowlisted", receiver_id)
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn fee(&self) -> Option<(Option<AccountId>, u128)> {
+        match self {
+            Transferability::WithFee { fee, receiver_id } if fee.0 > 0 => Some((receiver_id.clone(), fee.0)),
+            _ => None,
+        }
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -194,6 +239,9 @@ pub struct LicenseData {
 pub struct ShrinkedTokenLicense {
     pub id: String,
     pub metadata: ShrinkedLicenseData,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub status: Option<LicenseStatus>,
     // pub from: Option<SourceLicenseMeta>,
     // pub issued_at: Option<u64>, // When token was issued or minted, Unix epoch in milliseconds
     // pub expires_at: Option<u64>, // When token expires, Unix epoch in milliseconds
@@ -213,9 +261,82 @@ pub struct TokenLicense {
     pub expires_at: Option<u64>, // When token expires, Unix epoch in milliseconds
     pub starts_at: Option<u64>, // When token starts being valid, Unix epoch in milliseconds
     pub updated_at: Option<u64>, // When token was last updated, Unix epoch in milliseconds
+    #[serde(default)]
+    pub status: Option<LicenseStatus>, // Status set by the licensor, active if not set
+    #[serde(default)]
+    pub status_reason: Option<String>, // Why the licensor changed the status
+}
+
+// Lifecycle status of a license token.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseStatus {
+    #[default]
+    Active,
+    Suspended,
+    Revoked,
+    Expired,
+}
+
+impl fmt::Display for LicenseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LicenseStatus::Active => "active",
+            LicenseStatus::Suspended => "suspended",
+            LicenseStatus::Revoked => "revoked",
+            LicenseStatus::Expired => "expired",
+        };
+        f.write_str(name)
+    }
+}
+
+impl LicenseStatus {
+    // Checks a transition made by the licensor, expiration happens by time only
+    // and revocation is final.
+    pub fn check_transition(&self, new: LicenseStatus) -> Result<(), String> {
+        match (self, new) {
+            (LicenseStatus::Active, LicenseStatus::Suspended)
+            | (LicenseStatus::Suspended, LicenseStatus::Active)
+            | (LicenseStatus::Active, LicenseStatus::Revoked)
+            | (LicenseStatus::Suspended, LicenseStatus::Revoked)
+            | (LicenseStatus::Expired, LicenseStatus::Revoked) => Ok(()),
+            _ => Err(format!("Cannot change license status from {} to {}", self, new)),
+        }
+    }
+
+    // Terminated licenses no longer hold their asset objects.
+    pub fn is_terminated(&self) -> bool {
+        matches!(self, LicenseStatus::Revoked | LicenseStatus::Expired)
+    }
 }
 
 impl TokenLicense {
+    pub fn transferability(&self) -> Transferability {
+        self.metadata.transferability.clone().unwrap_or_default()
+    }
+
+    // Whether the license grants its rights at the given time, suspended
+    // and revoked licenses don't.
+    pub fn is_valid_at(&self, at: u64) -> bool {
+        if matches!(self.status, Some(LicenseStatus::Suspended) | Some(LicenseStatus::Revoked)) {
+            return false
+        }
+        !matches!(self.starts_at, Some(x) if x > at) && !matches!(self.expires_at, Some(x) if x <= at)
+    }
+
+    // Actual status of the license, taking expiration into account.
+    pub fn status(&self) -> LicenseStatus {
+        let status = self.status.unwrap_or_default();
+        if status == LicenseStatus::Revoked {
+            return status
+        }
+        match self.expires_at {
+            Some(expires_at) if expires_at <= env::block_timestamp_ms() => LicenseStatus::Expired,
+            _ => status,
+        }
+    }
+
     pub fn shrink(&self) -> ShrinkedTokenLicense {
         return ShrinkedTokenLicense{
             // expires_at: self.expires_at.clone(),
@@ -226,7 +347,8 @@ impl TokenLicense {
                 commercial_use: self.metadata.commercial_use.clone(),
                 personal_use: self.metadata.personal_use.clone(),
                 exclusivity: self.metadata.exclusivity.clone().unwrap_or(false),
-            }
+            },
+            status: Some(self.status()),
         }
     }
 }
@@ -249,19 +371,48 @@ pub struct Token {
     pub license: Option<TokenLicense>,
     pub metadata: TokenMetadata,
     //list of approved account IDs that have access to transfer the token. This maps an account ID to an approval ID
-    // pub approved_account_ids: HashMap<AccountId, u64>,
+    pub approved_account_ids: HashMap<AccountId, u64>,
     //the next approval ID to give out.
-    // pub next_approval_id: u64,
+    pub next_approval_id: u64,
     //keep track of the royalty percentages for the token in a hash map
-    // pub royalty: HashMap<AccountId, u32>,
+    pub royalty: HashMap<AccountId, u32>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize)]
-#[derive(Clone)]
+#[derive(Clone, Default)]
 #[serde(crate = "near_sdk::serde")]
 pub struct FilterOpt {
     pub account_id: Option<AccountId>,
     pub asset_id: Option<AssetId>,
+    #[serde(default)]
+    pub sku_id: Option<String>,
+    #[serde(default)]
+    pub license_id: Option<String>,
+    #[serde(default)]
+    pub exclusive: Option<bool>,
+    // whether the license is valid now, see TokenLicense::is_valid_at
+    #[serde(default)]
+    pub valid: Option<bool>,
+}
+
+impl FilterOpt {
+    pub fn matches(&self, token: &LicenseToken, at: u64) -> bool {
+        let license_id = token.license.as_ref().map(|x| x.id.clone());
+        let valid = token.license.as_ref().map(|x| x.is_valid_at(at)).unwrap_or(false);
+        filter_eq(&self.account_id, &token.owner_id)
+            && filter_eq(&self.asset_id, &token.asset_id)
+            && filter_eq(&self.sku_id, &token.sku_id())
+            && (self.license_id.is_none() || self.license_id == license_id)
+            && filter_eq(&self.exclusive, &token.is_exclusive())
+            && filter_eq(&self.valid, &valid)
+    }
+}
+
+fn filter_eq<T: PartialEq>(filter: &Option<T>, value: &T) -> bool {
+    match filter {
+        Some(x) => x == value,
+        None => true,
+    }
 }
 
 //The Json token is what will be returned from view calls.
@@ -272,6 +423,8 @@ pub struct ShrinkedLicenseToken {
     pub token_id: TokenId,
     //asset id of the token
     pub asset_id: AssetId,
+    //owner of the token
+    pub owner_id: Option<AccountId>,
     //token metadata
     pub metadata: ShrinkedTokenMetadata,
     // license metadata
@@ -279,6 +432,10 @@ pub struct ShrinkedLicenseToken {
 }
 
 impl ShrinkedLicenseToken {
+    pub fn license_status(&self) -> LicenseStatus {
+        self.license.as_ref().and_then(|x| x.status).unwrap_or_default()
+    }
+
     pub fn inventory_asset_license_sku(&self) -> (String, String, String, String) {
         if self.license.is_none() && self.metadata.from.is_none() {
             return (String::new(), String::new(), String::new(), String::new())
@@ -320,9 +477,11 @@ pub struct LicenseToken {
     // proposed license
     // pub proposed_license: TokenLicense,
     //list of approved account IDs that have access to transfer the token. This maps an account ID to an approval ID
-    // pub approved_account_ids: HashMap<AccountId, u64>,
+    #[serde(default)]
+    pub approved_account_ids: HashMap<AccountId, u64>,
     //keep track of the royalty percentages for the token in a hash map
-    // pub royalty: HashMap<AccountId, u32>,
+    #[serde(default)]
+    pub royalty: HashMap<AccountId, u32>,
 }
 
 impl LicenseToken {
@@ -353,6 +512,7 @@ impl LicenseToken {
         return ShrinkedLicenseToken{
             asset_id: self.asset_id.clone(),
             token_id: self.token_id.clone(),
+            owner_id: Some(self.owner_id.clone()),
             license: if self.license.is_some() { Some(self.license.as_ref().unwrap().shrink()) } else {None},
             metadata: self.metadata.shrink()
         }
@@ -443,6 +603,14 @@ impl LicenseGeneral for LicenseToken {
         objects.sort();
         return objects.join(",")
     }
+
+    fn owner_id(&self) -> Option<AccountId> {
+        Some(self.owner_id.clone())
+    }
+
+    fn issued_at(&self) -> Option<u64> {
+        self.metadata.issued_at
+    }
 }
 
 impl LicenseGeneral for ShrinkedLicenseToken {
@@ -523,12 +691,26 @@ impl LicenseGeneral for ShrinkedLicenseToken {
         objects.sort();
         return objects.join(",")
     }
+
+    fn owner_id(&self) -> Option<AccountId> {
+        self.owner_id.clone()
+    }
+
+    fn issued_at(&self) -> Option<u64> {
+        self.metadata.issued_at
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct NFTMintResult {
+    // token ID assigned to the mint, set even if minting fails
+    #[serde(default)]
+    pub token_id: Option<TokenId>,
     pub license_token: Option<LicenseToken>,
+    // purchase receipt of the mint, not set for gifts and failed mints
+    #[serde(default)]
+    pub receipt_id: Option<u64>,
     pub error: String,
 }
 
@@ -601,6 +783,12 @@ impl LicenseGeneral for InventoryLicense {
     fn object_hash(&self) -> String {
         String::new()
     }
+    fn owner_id(&self) -> Option<AccountId> {
+        None
+    }
+    fn issued_at(&self) -> Option<u64> {
+        None
+    }
 }
 
 impl InventoryLicense {
@@ -619,9 +807,11 @@ impl InventoryLicense {
                 starts_at: None,
                 updated_at: None,
                 uri: None,
+                status: None,
+                status_reason: None,
             }),
-            // approved_account_ids: Default::default(),
-            // royalty: Default::default(),
+            approved_account_ids: Default::default(),
+            royalty: Default::default(),
             owner_id: AccountId::new_unchecked("alice".to_string()),
             asset_id: String::new(),
             metadata: token_metadata,
@@ -637,6 +827,12 @@ pub struct SkuTokenData {
     pub params: Option<String> // Json-serialized AssetLicenseParams
 }
 
+impl SkuTokenData {
+    pub fn get_params(&self) -> AssetLicenseParams {
+        serde_json::from_str(&self.params.clone().unwrap_or("{}".to_string())).unwrap_or_default()
+    }
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
 #[serde(crate = "near_sdk::serde")]
 pub struct AssetLicense {
@@ -654,6 +850,9 @@ pub struct AssetLicense {
 }
 
 pub const NEAR_CURRENCY: &str = "NEAR";
+// NEAR costs of SKUs priced in other currencies are rounded to micro NEAR
+pub const NEAR_COST_DECIMALS: u8 = 6;
+pub const MS_IN_DAY: u64 = 24 * 60 * 60 * 1000;
 
 impl AssetLicense {
     pub fn get_near_cost(&self, near_usd_price: &Price) -> String {
@@ -663,19 +862,133 @@ impl AssetLicense {
         }
 
         if currency != NEAR_CURRENCY.to_string() {
-            // near cost = usd_price / near_price
-            let near_cost: f64 = self.price.clone().parse::<f64>().unwrap() / near_usd_price.float();
-            return format!("{:.6}", near_cost);
+            // near cost = usd_price / near_price, rounded half up to NEAR_COST_DECIMALS
+            let usd = amount_from_string(&self.price, NEAR_DECIMALS).unwrap_or_else(|msg| env::panic_str(&msg));
+            let (rate, rate_decimals) = near_usd_price.rate().unwrap_or_else(|| env::panic_str("Invalid NEAR price"));
+            let unit = 10u128.pow((NEAR_DECIMALS - NEAR_COST_DECIMALS) as u32);
+            let near_cost = rate.checked_mul(unit)
+                .and_then(|denominator| mul_div_round(usd, 10u128.pow(rate_decimals as u32), denominator))
+                .unwrap_or_else(|| env::panic_str("NEAR cost overflow"));
+            return amount_to_string(near_cost, NEAR_COST_DECIMALS);
         }
 
         return self.price.clone()
     }
 
+    // Price in the smallest units of a fungible token, the SKU must be priced in its currency.
+    pub fn get_ft_cost(&self, currency: &str, decimals: u8) -> Result<u128, String> {
+        let sku_currency = self.currency.clone().unwrap_or(NEAR_CURRENCY.to_string());
+        if sku_currency != currency {
+            return Err(format!(
+                "SKU {} is priced in {}, not in {}",
+                self.sku_id.clone().unwrap_or_default(), sku_currency, currency,
+            ))
+        }
+        amount_from_string(&self.price, decimals)
+    }
+
     pub fn get_params(&self) -> AssetLicenseParams {
         let res: AssetLicenseParams = serde_json::from_str(
             &self.params.clone().unwrap_or("{}".to_string())).unwrap_or_default();
         res
     }
+
+    // Expiration of a license of this SKU which is valid from the given time.
+    pub fn expires_at(&self, from: u64) -> Option<u64> {
+        self.get_params().duration_days.map(|days| from + days * MS_IN_DAY)
+    }
+}
+
+// Checks asset licenses against the inventory licenses and the asset objects.
+// Returns all found problems at once, one message per problem.
+pub fn validate_asset_licenses(
+    licenses: &[AssetLicense], inventory_licenses: &[InventoryLicense], metadata: &TokenMetadata,
+) -> Result<(), Vec<String>> {
+    let mut errors: Vec<String> = Vec::new();
+
+    // Objects are only taken into account when the asset has them, see issue_new_metadata
+    let object_ids: Option<Vec<String>> = match metadata.object.as_ref() {
+        Some(object) if !object.is_empty() => {
+            match serde_json::from_str::<ObjectData>(object) {
+                Ok(data) => Some(data.items.unwrap_or_default().into_iter().map(|x| x.id).collect()),
+                Err(_) => {
+                    errors.push("Failed parse asset object data".to_string());
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let mut sku_ids: Vec<String> = Vec::new();
+    for (i, license) in licenses.iter().enumerate() {
+        let sku_id = license.sku_id.clone().unwrap_or_default();
+        if sku_id.is_empty() {
+            errors.push(format!("License #{}: sku_id is not set", i));
+        } else if sku_ids.contains(&sku_id) {
+            errors.push(format!("SKU {}: duplicate sku_id", sku_id));
+        } else {
+            sku_ids.push(sku_id.clone());
+        }
+
+        let license_id = license.license_id.clone().unwrap_or_default();
+        if !license_id.is_empty() && !inventory_licenses.iter().any(|x| x.license_id == license_id) {
+            errors.push(format!("SKU {}: license_id {} not found in inventory licenses", sku_id, license_id));
+        }
+
+        if let Some(object_ids) = object_ids.as_ref() {
+            match license.objects.as_ref() {
+                None => errors.push(format!("SKU {}: objects are not set", sku_id)),
+                Some(objects) => {
+                    for object_id in objects {
+                        if !object_ids.contains(object_id) {
+                            errors.push(format!("SKU {}: object {} not found in asset objects", sku_id, object_id));
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(sole_limit) = license.sole_limit {
+            if sole_limit < 0 {
+                errors.push(format!("SKU {}: negative sole_limit {}", sku_id, sole_limit));
+            }
+        }
+
+        // prices are parsed exactly, in yocto units
+        if amount_from_string(&license.price, NEAR_DECIMALS).is_err() {
+            errors.push(format!("SKU {}: invalid price {}", sku_id, license.price));
+        }
+
+        let params = license.get_params();
+        if let Some(royalty) = params.royalty {
+            if royalty > 10_000 {
+                errors.push(format!("SKU {}: royalty {} is greater than 100%", sku_id, royalty));
+            }
+        }
+        if let Some(slippage_percents) = params.slippage_percents {
+            if slippage_percents > 100 {
+                errors.push(format!("SKU {}: slippage_percents {} is greater than 100", sku_id, slippage_percents));
+            }
+        }
+        if params.duration_days == Some(0) {
+            errors.push(format!("SKU {}: zero duration_days", sku_id));
+        }
+        if params.refund_window_days == Some(0) {
+            errors.push(format!("SKU {}: zero refund_window_days", sku_id));
+        }
+        if let Some(Transferability::Allowlisted { account_ids }) = params.transferability {
+            if account_ids.is_empty() {
+                errors.push(format!("SKU {}: transfer allowlist is empty", sku_id));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Default)]
@@ -683,6 +996,70 @@ impl AssetLicense {
 pub struct AssetLicenseParams {
     pub icon: Option<String>,
     pub description: Option<String>,
+    // perpetual royalty of the licensor on secondary sales, in basis points
+    pub royalty: Option<u32>,
+    // overrides the transfer rule of the inventory license
+    pub transferability: Option<Transferability>,
+    // license duration, the license never expires if not set
+    pub duration_days: Option<u64>,
+    // overrides the slippage of the contract settings
+    pub slippage_percents: Option<u32>,
+    // cooling-off period after the mint during which the buyer can return the license
+    pub refund_window_days: Option<u64>,
+}
+
+// Total of the payee shares of a revenue split, 100% in basis points.
+pub const REVENUE_SPLIT_BPS: u32 = 10_000;
+// Most payees of a revenue split, every one of them is credited in the mint callbacks.
+pub const MAX_REVENUE_PAYEES: usize = 10;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payee {
+    pub account_id: AccountId,
+    // share of the sale proceeds in basis points
+    pub bps: u32,
+}
+
+// Checks that the payees are unique and their shares sum up to 100%.
+pub fn validate_revenue_split(payees: &[Payee]) -> Result<(), String> {
+    if payees.is_empty() {
+        return Err("Revenue split has no payees".to_string())
+    }
+    if payees.len() > MAX_REVENUE_PAYEES {
+        return Err(format!("Revenue split has {} payees, at most {} allowed", payees.len(), MAX_REVENUE_PAYEES))
+    }
+    let mut total: u32 = 0;
+    for (i, payee) in payees.iter().enumerate() {
+        if payee.bps == 0 {
+            return Err(format!("Payee {}: zero share", payee.account_id))
+        }
+        if payees[..i].iter().any(|x| x.account_id == payee.account_id) {
+            return Err(format!("Payee {}: duplicate account", payee.account_id))
+        }
+        total = total.saturating_add(payee.bps);
+    }
+    if total != REVENUE_SPLIT_BPS {
+        return Err(format!("Revenue split sums up to {} bps instead of {}", total, REVENUE_SPLIT_BPS))
+    }
+    Ok(())
+}
+
+// Splits the amount between the payees by their shares,
+// the rounding remainder goes to the last payee.
+pub fn split_revenue(amount: u128, payees: &[Payee]) -> Vec<(AccountId, u128)> {
+    let mut rest = amount;
+    let mut shares: Vec<(AccountId, u128)> = Vec::new();
+    for (i, payee) in payees.iter().enumerate() {
+        let share = if i == payees.len() - 1 {
+            rest
+        } else {
+            (amount * payee.bps as u128 / REVENUE_SPLIT_BPS as u128).min(rest)
+        };
+        rest -= share;
+        shares.push((payee.account_id.clone(), share));
+    }
+    shares
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -709,6 +1086,9 @@ pub struct InventoryContractMetadata {
     // pub reference_hash: Option<Base64VecU8>, // Base64-encoded sha256 hash of JSON from reference field. Required if `reference` is included.
     pub licenses: Vec<InventoryLicense>,            // required, ex. "MOSIAC"
     pub default_minter_id: String,
+    // payees of the assets without own revenue split
+    #[serde(default)]
+    pub revenue_split: Option<Vec<Payee>>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -725,6 +1105,7 @@ pub struct AssetToken {
     pub licenses: Option<Vec<AssetLicense>>,
     pub policy_rules: Option<Vec<LimitationData>>,
     pub upgrade_rules: Option<Vec<PolicyData>>,
+    pub revenue_split: Option<Vec<Payee>>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
@@ -743,6 +1124,9 @@ pub struct JsonAssetToken {
     pub licenses: Option<Vec<AssetLicense>>,
     pub policy_rules: Option<Vec<LimitationData>>,
     pub upgrade_rules: Option<Vec<PolicyData>>,
+    // payees of the sale proceeds, the inventory split is used if not set
+    #[serde(default)]
+    pub revenue_split: Option<Vec<Payee>>,
     // pub available_licenses: Option<Vec<InventoryLicenseAvailability>>
 }
 
@@ -757,11 +1141,19 @@ impl Default for JsonAssetToken {
             metadata: TokenMetadata::default(),
             token_id: String::new(),
             license_token_count: 0,
+            revenue_split: None,
         }
     }
 }
 
 impl JsonAssetToken {
+    // Payees of the asset sale: own split of the asset, then the inventory split,
+    // otherwise everything goes to the fallback account.
+    pub fn revenue_payees(&self, inventory_split: Option<&Vec<Payee>>, fallback: AccountId) -> Vec<Payee> {
+        self.revenue_split.as_ref().or(inventory_split).cloned()
+            .unwrap_or_else(|| vec![Payee{account_id: fallback, bps: REVENUE_SPLIT_BPS}])
+    }
+
     pub fn issue_new_metadata(&self, sku_info: AssetLicense) -> TokenMetadata {
         let mut metadata = self.metadata.clone();
         metadata.issued_at = Some(env::block_timestamp_ms());
@@ -814,8 +1206,10 @@ impl JsonAssetToken {
     }
 
     pub fn issue_new_license(&self, inv_license: Option<InventoryLicense>, sku_info: AssetLicense, token_id: String) -> LicenseToken {
-        let metadata = self.issue_new_metadata(sku_info.clone());
-        let license: Option<TokenLicense>;
+        let mut metadata = self.issue_new_metadata(sku_info.clone());
+        let expires_at = sku_info.expires_at(env::block_timestamp_ms());
+        metadata.expires_at = expires_at;
+        let mut license: Option<TokenLicense>;
 
         if let Some(inv_license) = inv_license {
             license = Some(TokenLicense{
@@ -827,18 +1221,24 @@ impl JsonAssetToken {
                 issued_at: Some(env::block_timestamp_ms()),
                 starts_at: Some(env::block_timestamp_ms()),
                 updated_at: Some(env::block_timestamp_ms()),
-                expires_at: None,
+                expires_at,
+                status: None,
+                status_reason: None,
             });
         } else {
             license = None
         }
+        if let (Some(license), Some(transferability)) = (license.as_mut(), sku_info.get_params().transferability) {
+            license.metadata.transferability = Some(transferability);
+        }
         LicenseToken{
             asset_id: self.token_id.clone(),
             token_id: token_id.clone(),
             license,
             owner_id: AccountId::new_unchecked("alice".to_string()),
             metadata,
-            // approved_account_ids: Default::default(),
+            approved_account_ids: Default::default(),
+            royalty: Default::default(),
         }
     }
 }
@@ -858,6 +1258,8 @@ pub struct SKUAvailability {
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct FullInventory {
+    // inventory account, used to look up the inventory's own registered policies
+    pub inventory_id: Option<AccountId>,
     pub inventory_licenses: Vec<InventoryLicense>,
     pub issued_licenses:    Vec<ShrinkedLicenseToken>,
     pub asset: Option<JsonAssetToken>,
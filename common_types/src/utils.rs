@@ -2,7 +2,7 @@ use near_sdk::{Balance, AccountId, env, Promise};
 use near_sdk::serde_json;
 use crate::types::ObjectData;
 
-pub fn refund_storage(initial_storage: u64, predecessor_id: Option<AccountId>, charged_price: Option<Balance>) -> Result<(), String> {
+pub fn refund_storage(initial_storage: u64, predecessor_id: Option<AccountId>, charged_price: Option<Balance>) -> Result<Balance, String> {
     let new_storage_usage = env::storage_usage();
     let mut storage_usage_diff =  0 as near_sdk::StorageUsage;
     if new_storage_usage > initial_storage {
@@ -13,8 +13,8 @@ pub fn refund_storage(initial_storage: u64, predecessor_id: Option<AccountId>, c
     return refund_deposit(storage_usage_diff, predecessor_id, charged_price)
 }
 
-// refund the initial deposit based on the amount of storage that was used up
-pub fn refund_deposit(storage_used: u64, predecessor_id: Option<AccountId>, charged_price: Option<Balance>) -> Result<(), String> {
+// refund the initial deposit based on the amount of storage that was used up, returns the refunded amount
+pub fn refund_deposit(storage_used: u64, predecessor_id: Option<AccountId>, charged_price: Option<Balance>) -> Result<Balance, String> {
     // get how much it would cost to store the information
     let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
     // get the attached deposit
@@ -42,7 +42,7 @@ pub fn refund_deposit(storage_used: u64, predecessor_id: Option<AccountId>, char
     if refund > 1 {
         Promise::new(predecessor_account_id).transfer(refund);
     }
-    Ok(())
+    Ok(refund)
 }
 
 //Assert that the user has attached at least 1 yoctoNEAR (for security reasons and to pay for storage)
@@ -61,19 +61,91 @@ pub fn assert_one_yocto() {
     )
 }
 
+pub const NEAR_DECIMALS: u8 = 24;
+
+// Parses a NEAR amount into yoctoNEAR, digits after the 24th decimal are dropped.
 pub fn balance_from_string(s: String) -> near_sdk::Balance {
-    let float: f64 = s.parse().unwrap();
-    let half_yocto = float * 1e12;
-    let half = half_yocto as u128;
-    use std::ops::Mul;
-    let near = half.mul(1e12 as u128);
-    return near
+    let s = match s.trim().split_once('.') {
+        Some((integer, fraction)) if fraction.len() > NEAR_DECIMALS as usize => {
+            format!("{}.{}", integer, fraction.get(..NEAR_DECIMALS as usize).unwrap_or(fraction))
+        }
+        _ => s,
+    };
+    amount_from_string(&s, NEAR_DECIMALS).unwrap_or_else(|msg| env::panic_str(&msg))
+}
+
+// Parses a decimal amount into the smallest units of a token with the given decimals, e.g.
+// "1.5" with 6 decimals is 1500000.
+pub fn amount_from_string(s: &str, decimals: u8) -> Result<u128, String> {
+    let invalid = || format!("Invalid amount {}", s);
+    let (integer, fraction) = s.trim().split_once('.').unwrap_or((s.trim(), ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(invalid())
+    }
+    if !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(invalid())
+    }
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return Err(format!("Amount {} has more than {} decimals", s, decimals))
+    }
+    let digits = format!("{}{}{}", integer, fraction, "0".repeat(decimals as usize - fraction.len()));
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return Ok(0)
+    }
+    digits.parse::<u128>().map_err(|_| invalid())
+}
+
+// Formats an amount in the smallest units of a token with the given decimals,
+// without trailing zeros, e.g. 1500000 with 6 decimals is "1.5".
+pub fn amount_to_string(amount: u128, decimals: u8) -> String {
+    let unit = 10u128.pow(decimals as u32);
+    let fraction = format!("{:0width$}", amount % unit, width = decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (amount / unit).to_string()
+    } else {
+        format!("{}.{}", amount / unit, fraction)
+    }
+}
+
+// a * b / c rounded half up, None on overflow or zero c
+pub fn mul_div_round(a: u128, b: u128, c: u128) -> Option<u128> {
+    if c == 0 {
+        return None
+    }
+    // a * b / c == (a / c) * b + (a % c) * b / c
+    let whole = (a / c).checked_mul(b)?;
+    // (a % c) * b can exceed u128 for yoctoNEAR amounts, so it is divided bit by bit:
+    // x * (the bits of b seen so far) == q * c + r, with r < c
+    let x = a % c;
+    let (mut q, mut r) = (0u128, 0u128);
+    for bit in (0..128).rev() {
+        q = q.checked_mul(2)?;
+        if r >= c - r {
+            r -= c - r;
+            q = q.checked_add(1)?;
+        } else {
+            r *= 2;
+        }
+        if b >> bit & 1 == 1 {
+            if r >= c - x {
+                r -= c - x;
+                q = q.checked_add(1)?;
+            } else {
+                r += x;
+            }
+        }
+    }
+    if r >= c - r {
+        q = q.checked_add(1)?;
+    }
+    whole.checked_add(q)
 }
 
 pub fn format_balance(b: near_sdk::Balance) -> String {
-    let half = b / 1e12 as u128;
-    let float = (half as f64) / 1e12;
-    float.to_string()
+    amount_to_string(b, NEAR_DECIMALS)
 }
 
 pub fn get_inventory_id(minter_id: String) -> String {
@@ -1,6 +1,14 @@
+use near_sdk::{PromiseError};
 use common_types::utils::assert_one_yocto;
 use crate::*;
 
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetLicensesResult {
+    pub licenses: Option<Vec<AssetLicense>>,
+    pub error: Option<String>,
+}
+
 #[near_bindgen]
 impl InventoryContract {
     #[payable]
@@ -8,19 +16,170 @@ impl InventoryContract {
         &mut self,
         token_id: String,
         licenses: Vec<AssetLicense>,
-    ) -> Vec<AssetLicense> {
+    ) -> PromiseOrValue<AssetLicensesResult> {
         let initial_storage_usage = env::storage_usage();
 
         self.ensure_owner();
 
-        let mut asset = self.tokens_by_id.get(&token_id).expect("No such token");
+        let asset = self.tokens_by_id.get(&token_id).expect("No such token");
+        let metadata = self.token_metadata_by_id.get(&token_id).expect("No such token");
+        self.ensure_valid_licenses(&licenses, &metadata);
+
+        if asset.license_token_count == 0 {
+            let result = self.internal_update_licenses(&token_id, licenses);
+            let _ = refund_storage(initial_storage_usage, None, None);
+            return PromiseOrValue::Value(result)
+        }
+
+        // Licenses were already issued: they must still fit the new configuration
+        let issued = license_contract::ext(asset.minter_id.clone())
+            .with_unused_gas_weight(3)
+            .shrinked_nft_tokens_for_asset(token_id.clone());
+        let on_issued_promise = issued.then(
+            Self::ext(env::current_account_id())
+                .with_attached_deposit(env::attached_deposit())
+                .with_unused_gas_weight(27)
+                .on_asset_licenses_issued(
+                    token_id,
+                    licenses,
+                    env::predecessor_account_id(),
+                )
+        );
+        PromiseOrValue::Promise(on_issued_promise)
+    }
+
+    #[private]
+    pub fn on_asset_licenses_issued(
+        &mut self,
+        #[callback_result] issued_res: Result<Vec<ShrinkedLicenseToken>, PromiseError>,
+        token_id: String,
+        licenses: Vec<AssetLicense>,
+        predecessor_id: AccountId,
+    ) -> PromiseOrValue<AssetLicensesResult> {
+        let initial_storage_usage = env::storage_usage();
+
+        let asset = self.tokens_by_id.get(&token_id);
+        let metadata = self.token_metadata_by_id.get(&token_id);
+        if issued_res.is_err() || asset.is_none() || metadata.is_none() {
+            let _ = refund_storage(initial_storage_usage, Some(predecessor_id), None);
+            let msg = if issued_res.is_err() { "Failed call shrinked_nft_tokens_for_asset()" } else { "No such token" };
+            return PromiseOrValue::Value(AssetLicensesResult{licenses: None, error: Some(msg.to_string())})
+        }
+        let asset = asset.unwrap();
+
+        let inventory = FullInventory{
+            inventory_id: Some(env::current_account_id()),
+            inventory_licenses: self.metadata.get().unwrap().licenses,
+            issued_licenses: issued_res.unwrap(),
+            asset: Some(JsonAssetToken{
+                token_id: token_id.clone(),
+                owner_id: asset.owner_id,
+                minter_id: asset.minter_id,
+                license_token_count: asset.license_token_count,
+                metadata: metadata.unwrap(),
+                licenses: asset.licenses,
+                policy_rules: asset.policy_rules,
+                upgrade_rules: asset.upgrade_rules,
+                revenue_split: asset.revenue_split,
+            }),
+        };
+        let check_change = policy_rules_contract::ext(self.policy_contract.clone())
+            .with_unused_gas_weight(3)
+            .simulate_asset_change(inventory, Some(licenses.clone()), None);
+        let on_check_promise = check_change.then(
+            Self::ext(env::current_account_id())
+                .with_attached_deposit(env::attached_deposit())
+                .with_unused_gas_weight(27)
+                .on_check_asset_change(
+                    token_id,
+                    licenses,
+                    predecessor_id,
+                )
+        );
+        PromiseOrValue::Promise(on_check_promise)
+    }
+
+    #[private]
+    pub fn on_check_asset_change(
+        &mut self,
+        #[callback_result] check_res: Result<AssetChangeReportData, PromiseError>,
+        token_id: String,
+        licenses: Vec<AssetLicense>,
+        predecessor_id: AccountId,
+    ) -> AssetLicensesResult {
+        let initial_storage_usage = env::storage_usage();
+
+        if check_res.is_err() {
+            let _ = refund_storage(initial_storage_usage, Some(predecessor_id), None);
+            return AssetLicensesResult{licenses: None, error: Some("Failed call simulate_asset_change()".to_string())}
+        }
+        let report = check_res.unwrap();
+        if !report.violations.is_empty() {
+            let _ = refund_storage(initial_storage_usage, Some(predecessor_id), None);
+            let errors: Vec<String> = report.violations.iter().map(
+                |x| format!("token {} (SKU {}): {}", x.token_id, x.sku_id, x.reason)
+            ).collect();
+            let msg = format!("Issued licenses do not fit the new licenses: {}", errors.join("; "));
+            return AssetLicensesResult{licenses: None, error: Some(msg)}
+        }
+        if self.tokens_by_id.get(&token_id).is_none() {
+            let _ = refund_storage(initial_storage_usage, Some(predecessor_id), None);
+            return AssetLicensesResult{licenses: None, error: Some("No such token".to_string())}
+        }
+
+        let result = self.internal_update_licenses(&token_id, licenses);
+        let _ = refund_storage(initial_storage_usage, Some(predecessor_id), None);
+        result
+    }
+
+    pub(crate) fn internal_update_licenses(&mut self, token_id: &String, licenses: Vec<AssetLicense>) -> AssetLicensesResult {
+        let mut asset = self.tokens_by_id.get(token_id).expect("No such token");
 
         asset.licenses = Some(licenses);
-        self.tokens_by_id.insert(&token_id, &asset);
+        self.tokens_by_id.insert(token_id, &asset);
+
+        AssetLicensesResult{licenses: asset.licenses, error: None}
+    }
+
+    // Sets the payees of the asset sales, the inventory revenue split is used if none.
+    #[payable]
+    pub fn asset_set_revenue_split(&mut self, token_id: String, revenue_split: Option<Vec<Payee>>) -> JsonAssetToken {
+        let initial_storage_usage = env::storage_usage();
+
+        self.ensure_owner();
+        if self.tokens_by_id.get(&token_id).is_none() {
+            env::panic_str("Token does not exist")
+        }
+        self.ensure_valid_revenue_split(revenue_split.as_ref());
+        self.internal_set_revenue_split(&token_id, revenue_split);
 
         let _ = refund_storage(initial_storage_usage, None, None);
+        self._asset_token(token_id).unwrap()
+    }
+
+    pub(crate) fn internal_set_revenue_split(&mut self, token_id: &String, revenue_split: Option<Vec<Payee>>) {
+        let mut asset = self.tokens_by_id.get(token_id).expect("No such token");
+
+        asset.revenue_split = revenue_split;
+        self.tokens_by_id.insert(token_id, &asset);
+    }
+
+    // Suspends, reinstates or revokes a license issued for the asset.
+    // The license contract checks that the status transition is allowed.
+    #[payable]
+    pub fn asset_set_license_status(
+        &mut self,
+        token_id: String,
+        license_token_id: String,
+        status: LicenseStatus,
+        reason: Option<String>,
+    ) -> Promise {
+        self.ensure_owner();
+        let asset = self.tokens_by_id.get(&token_id).expect("No such token");
 
-        asset.licenses.unwrap().clone()
+        license_contract::ext(asset.minter_id)
+            .with_attached_deposit(env::attached_deposit())
+            .nft_set_license_status(license_token_id, status, reason)
     }
 
     #[payable]
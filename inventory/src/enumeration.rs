@@ -27,6 +27,7 @@ impl InventoryContract {
             license_token_count: token.license_token_count,
             policy_rules: token.policy_rules,
             upgrade_rules: token.upgrade_rules,
+            revenue_split: token.revenue_split,
         };
         Some(asset)
     }
@@ -1,5 +1,6 @@
 use crate::*;
 use near_sdk::{CryptoHash};
+use common_types::types::{Payee, validate_asset_licenses, validate_revenue_split};
 
 //used to generate a unique prefix in our storage collections (this is to avoid data collisions)
 pub(crate) fn hash_account_id(account_id: &AccountId) -> CryptoHash {
@@ -12,6 +13,23 @@ pub(crate) fn hash_account_id(account_id: &AccountId) -> CryptoHash {
 
 #[near_bindgen]
 impl InventoryContract {
+    //panic with the list of all problems found in the asset licenses
+    pub(crate) fn ensure_valid_licenses(&self, licenses: &[AssetLicense], metadata: &TokenMetadata) {
+        let inventory_licenses = self.metadata.get().unwrap().licenses;
+        if let Err(errors) = validate_asset_licenses(licenses, &inventory_licenses, metadata) {
+            env::panic_str(&format!("Invalid asset licenses: {}", errors.join("; ")))
+        }
+    }
+
+    //panic if the payees do not share exactly 100% of the proceeds
+    pub(crate) fn ensure_valid_revenue_split(&self, revenue_split: Option<&Vec<Payee>>) {
+        if let Some(payees) = revenue_split {
+            if let Err(msg) = validate_revenue_split(payees) {
+                env::panic_str(&format!("Invalid revenue split: {}", msg))
+            }
+        }
+    }
+
     //add a token to the set of tokens an owner has
     pub(crate) fn internal_add_token_to_owner(
         &mut self,
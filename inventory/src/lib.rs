@@ -1,15 +1,16 @@
-use near_sdk::{AccountId, CryptoHash, env, ext_contract, Gas, near_bindgen, PanicOnDefault, Promise};
+use near_sdk::{AccountId, CryptoHash, env, ext_contract, Gas, near_bindgen, PanicOnDefault, Promise, PromiseOrValue};
 // use std::collections::HashMap;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
 use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 
-use common_types::policy::IsAvailableResponseData;
+use common_types::policy::{AssetChangeReportData, IsAvailableResponseData, LimitationData, PolicyData};
 pub use common_types::types::{AssetToken, TokenMetadata};
 pub use common_types::types::{AssetLicense, FilterOpt, SKUAvailability};
 pub use common_types::types::{InventoryContractMetadata, InventoryLicense};
 pub use common_types::types::{JsonAssetToken, LicenseToken, TokenId};
+pub use common_types::types::{FullInventory, LicenseStatus, Payee, ShrinkedLicenseToken};
 use common_types::utils::{refund_storage};
 
 pub use crate::events::*;
@@ -70,11 +71,20 @@ pub enum StorageKey {
 pub trait LicenseContract {
     fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>, filter_opt: Option<FilterOpt>) -> Vec<LicenseToken>;
     fn nft_token(&self, token_id: TokenId) -> Option<LicenseToken>;
+    fn shrinked_nft_tokens_for_asset(&self, asset_id: String) -> Vec<ShrinkedLicenseToken>;
+    fn nft_set_license_status(&mut self, token_id: TokenId, status: LicenseStatus, reason: Option<String>);
 }
 
 #[ext_contract(policy_rules_contract)]
 pub trait PolicyRulesContract {
     fn check_inventory_state(&self, licenses: Vec<InventoryLicense>) -> IsAvailableResponseData;
+    fn register_inventory_policies(
+        &mut self, policy_rules: Option<Vec<LimitationData>>, upgrade_rules: Option<Vec<PolicyData>>,
+        predecessor_id: Option<AccountId>);
+    fn unregister_inventory_policies(&mut self, predecessor_id: Option<AccountId>);
+    fn simulate_asset_change(
+        &self, inventory: FullInventory, new_asset_licenses: Option<Vec<AssetLicense>>,
+        new_policy_rules: Option<Vec<LimitationData>>) -> AssetChangeReportData;
 }
 
 #[near_bindgen]
@@ -99,6 +109,7 @@ impl InventoryContract {
                 description: None,
                 licenses: Vec::new(),
                 default_minter_id: "".to_string(),
+                revenue_split: None,
             },
         )
     }
@@ -221,6 +232,7 @@ impl InventoryContract {
             }
             let asset_token = self.tokens_by_id.get(&token.token_id);
             self._on_nft_mint(asset_token.unwrap().clone(), token.license_token_count);
+            self.internal_set_revenue_split(&token.token_id, token.revenue_split);
 
         }
 
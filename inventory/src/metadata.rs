@@ -46,6 +46,7 @@ impl InventoryMetadata for InventoryContract {
     #[payable]
     fn update_inventory_metadata(&mut self, metadata: InventoryContractMetadata) -> Promise {
         self.ensure_owner();
+        self.ensure_valid_revenue_split(metadata.revenue_split.as_ref());
 
         let check_state = policy_rules_contract::ext(self.policy_contract.clone())
             .with_unused_gas_weight(3).check_inventory_state(
@@ -122,6 +123,54 @@ pub struct MetadataResult {
 
 #[near_bindgen]
 impl InventoryContract {
+    // Registers the inventory-wide base policies in the policy contract.
+    // They apply to every asset on top of the global policies.
+    #[payable]
+    pub fn set_inventory_policies(
+        &mut self,
+        policy_rules: Option<Vec<LimitationData>>,
+        upgrade_rules: Option<Vec<PolicyData>>,
+    ) -> Promise {
+        self.ensure_owner();
+
+        policy_rules_contract::ext(self.policy_contract.clone())
+            .with_attached_deposit(env::attached_deposit())
+            .register_inventory_policies(
+                policy_rules,
+                upgrade_rules,
+                Some(env::predecessor_account_id()),
+            )
+    }
+
+    // Removes the inventory-wide base policies from the policy contract,
+    // the released storage is refunded to the owner.
+    pub fn clear_inventory_policies(&mut self) -> Promise {
+        self.ensure_owner();
+
+        policy_rules_contract::ext(self.policy_contract.clone())
+            .unregister_inventory_policies(Some(env::predecessor_account_id()))
+    }
+
+    // Sets the payees of the sales of assets without own revenue split.
+    #[payable]
+    pub fn set_inventory_revenue_split(&mut self, revenue_split: Option<Vec<Payee>>) -> ExtendedInventoryMetadata {
+        let initial_storage_usage = env::storage_usage();
+
+        self.ensure_owner();
+        self.ensure_valid_revenue_split(revenue_split.as_ref());
+
+        let mut meta = self.metadata.get().unwrap();
+        meta.revenue_split = revenue_split;
+        self.metadata.replace(&meta);
+
+        let _ = refund_storage(initial_storage_usage, None, None);
+        ExtendedInventoryMetadata {
+            metadata: self.metadata.get().unwrap(),
+            asset_count: self.token_metadata_by_id.len(),
+            owner_id: self.owner_id.clone(),
+        }
+    }
+
     #[private]
     pub(crate) fn _update_inventory_metadata(
         &mut self,
@@ -41,6 +41,7 @@ impl InventoryContract {
             license_token_count: 0,
             policy_rules: policy_rules.clone(),
             upgrade_rules: upgrade_rules.clone(),
+            revenue_split: None,
         }
     }
 
@@ -55,6 +56,9 @@ impl InventoryContract {
         upgrade_rules: Option<Vec<PolicyData>>,
     ) -> EventLog {
         self.ensure_owner();
+        if let Some(licenses) = licenses.as_ref() {
+            self.ensure_valid_licenses(licenses, &metadata);
+        }
 
         let token = AssetToken {
             token_id: token_id.clone(),
@@ -64,6 +68,7 @@ impl InventoryContract {
             policy_rules: policy_rules.clone(),
             upgrade_rules: upgrade_rules.clone(),
             licenses,
+            revenue_split: None,
         };
 
         //insert the token ID and token struct and make sure that the token doesn't exist
@@ -120,6 +125,7 @@ impl InventoryContract {
             policy_rules: policy_rules.clone(),
             upgrade_rules: upgrade_rules.clone(),
             licenses,
+            revenue_split: old_token.revenue_split,
         };
 
         self.tokens_by_id.insert(&token_id, &token);
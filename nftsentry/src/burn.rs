@@ -0,0 +1,79 @@
+use near_sdk::Gas;
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    //burns the token, only the token owner can call it
+    #[payable]
+    pub fn nft_burn(&mut self, token_id: TokenId, memo: Option<String>) {
+        //assert that the user attached exactly 1 yoctoNEAR for security reasons
+        assert_one_yocto();
+        let token = self.tokens_by_id.get(&token_id).expect("No token");
+        if env::predecessor_account_id() != token.owner_id {
+            env::panic_str("Only the token owner can burn the token")
+        }
+
+        self.internal_burn(&token_id, None, memo);
+    }
+
+    //cancels the license of the token by burning it, only the contract owner can call it
+    #[payable]
+    pub fn nft_cancel_license(&mut self, token_id: TokenId, memo: Option<String>) {
+        assert_one_yocto();
+        let sender = env::predecessor_account_id();
+        if sender != self.owner_id {
+            env::panic_str("Unauthorized")
+        }
+
+        self.internal_burn(&token_id, Some(sender), memo);
+    }
+}
+
+impl Contract {
+    //removes the token from all the collections, refunds the released storage to the token owner
+    //and updates the license count of the asset in the inventory
+    pub(crate) fn internal_burn(&mut self, token_id: &TokenId, authorized_id: Option<AccountId>, memo: Option<String>) -> Token {
+        let initial_storage_usage = env::storage_usage();
+        let token = self.tokens_by_id.remove(token_id).expect("No token");
+
+        self.internal_remove_token_from_owner(&token.owner_id, token_id);
+        self.internal_remove_token_from_asset(&token.asset_id, token_id);
+        self.internal_unindex_token(&token);
+        self.internal_add_token_history(token_id, TokenHistoryKind::Burn, &token.owner_id, None);
+
+        //the released storage includes the approved account IDs
+        let storage_released = initial_storage_usage.saturating_sub(env::storage_usage());
+        let refund = Balance::from(storage_released) * env::storage_byte_cost();
+        if refund > 0 {
+            Promise::new(token.owner_id.clone()).transfer(refund);
+        }
+
+        //the burned license no longer counts against the asset limits, e.g. exclusive objects are free again
+        let license_sold = self.nft_token_supply_for_asset(token.asset_id.clone());
+        inventory_contract::ext(self.inventory_id.clone()).with_static_gas(Gas::ONE_TERA * 3).on_nft_mint(
+            token.asset_id.clone(), license_sold
+        );
+
+        // Construct the burn log as per the events standard.
+        let nft_burn_log: EventLog = EventLog {
+            // Standard name ("nep171").
+            standard: NFT_STANDARD_NAME.to_string(),
+            // Version of the standard ("nft-1.0.0").
+            version: NFT_METADATA_SPEC.to_string(),
+            // The data related with the event stored in a vector.
+            event: EventLogVariant::NftBurn(vec![NftBurnLog {
+                // Owner of the token.
+                owner_id: token.owner_id.to_string(),
+                // The optional account ID that burned the token on behalf of the owner.
+                authorized_id: authorized_id.map(|x| x.to_string()),
+                // Vector of token IDs that were burned.
+                token_ids: vec![token_id.to_string()],
+                // An optional memo to include.
+                memo,
+            }]),
+        };
+        self.log_event(&nft_burn_log.to_string());
+
+        token
+    }
+}
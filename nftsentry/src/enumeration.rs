@@ -1,5 +1,15 @@
-use std::str::FromStr;
 use crate::*;
+use crate::internal::*;
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokensPage {
+    pub tokens: Vec<LicenseToken>,
+    //position in the index to continue from, not set on the last page.
+    //burning or transferring a token moves the last token of the index into its place,
+    //so tokens can be skipped or repeated if the index changes between the pages
+    pub next_cursor: Option<U128>,
+}
 
 #[near_bindgen]
 impl Contract {
@@ -13,28 +23,43 @@ impl Contract {
         return self.benefit_config.clone()
     }
 
+    //get the license transfer rule of the token
+    pub fn nft_transferability(&self, token_id: TokenId) -> Option<Transferability> {
+        self.tokens_by_id.get(&token_id).map(|token| token_transferability(&token))
+    }
+
     //Query for nft tokens on the contract regardless of the owner using pagination
     pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>, filter_opt: Option<FilterOpt>) -> Vec<LicenseToken> {
         //where to start pagination - if we have a from_index, we'll use that - otherwise start from 0 index
         let start = u128::from(from_index.unwrap_or(U128(0)));
 
+        //filtered queries are served from the indices, from_index counts the matching tokens
+        if let Some(filter) = filter_opt {
+            return self.internal_filter_tokens(&filter, 0, start as usize, limit.unwrap_or(50) as usize).tokens
+        }
+
         //iterate through each token using an iterator
-        let is_filter = filter_opt.as_ref().is_some();
-        let is_asset_filter = is_filter && filter_opt.as_ref().unwrap().asset_id.is_some();
-        let is_owner_filter = is_filter && filter_opt.as_ref().unwrap().account_id.is_some();
         self.tokens_by_id.keys()
-            //we'll map the token IDs which are strings into Json Tokens
-            .map(|token_id| self.nft_token(token_id.clone()).unwrap())
-            .filter(|x| !is_asset_filter || *filter_opt.as_ref().unwrap().asset_id.as_ref().unwrap() == x.asset_id)
-            .filter(|x| !is_owner_filter || *filter_opt.as_ref().unwrap().account_id.as_ref().unwrap() == x.owner_id)
             //skip to the index we specified in the start variable
             .skip(start as usize) 
             //take the first "limit" elements in the vector. If we didn't specify a limit, use 50
             .take(limit.unwrap_or(50) as usize)
+            //we'll map the token IDs which are strings into Json Tokens
+            .map(|token_id| self.nft_token(token_id).unwrap())
             //since we turned the keys into an iterator, we need to turn it back into a vector to return
             .collect()
     }
 
+    //Query for nft tokens matching the filter using cursor-based pagination,
+    //pass next_cursor of the previous page to get the next one.
+    //The cursor is a position in the index, not a token ID: the pages are only consistent
+    //while no token leaves the index, otherwise dedupe by token ID and restart if it matters
+    pub fn nft_tokens_page(&self, filter_opt: Option<FilterOpt>, cursor: Option<U128>, limit: Option<u64>) -> TokensPage {
+        let filter = filter_opt.unwrap_or_default();
+        let cursor = u128::from(cursor.unwrap_or(U128(0))) as u64;
+        self.internal_filter_tokens(&filter, cursor, 0, limit.unwrap_or(50) as usize)
+    }
+
     pub fn shrinked_nft_tokens_for_asset(&self, asset_id: String) -> Vec<ShrinkedLicenseToken> {
         let mut result: Vec<ShrinkedLicenseToken> = Vec::new();
         let tokens_opt = self.tokens_per_asset.get(&asset_id);
@@ -66,6 +91,7 @@ impl Contract {
         ShrinkedLicenseToken {
             token_id,
             asset_id: token.asset_id,
+            owner_id: Some(token.owner_id),
             metadata: token.metadata.shrink(),
             license: if token.license.is_some() { Some(token.license.as_ref().unwrap().shrink()) } else {None},
         }
@@ -89,19 +115,7 @@ impl Contract {
     }
 
     pub fn nft_token_id_max(&self) -> String {
-        let mut max: i64 = 0;
-        for key in self.tokens_by_id.keys() {
-            let current = i64::from_str(&key);
-            if current.is_err() {
-                continue
-            } else {
-                let res = current.unwrap();
-                if res > max {
-                    max = res.clone()
-                }
-            }
-        }
-        max.to_string()
+        (self.next_token_id - 1).to_string()
     }
 
     //Query for all the tokens for an owner
@@ -136,3 +150,58 @@ impl Contract {
             .collect()
     }
 }
+
+impl Contract {
+    //the most selective index for the filter: None if the filter has no indexed field,
+    //Some(None) if the index has no tokens for it
+    fn internal_token_index(&self, filter: &FilterOpt) -> Option<Option<UnorderedSet<TokenId>>> {
+        if let (Some(account_id), Some(asset_id)) = (filter.account_id.as_ref(), filter.asset_id.as_ref()) {
+            return Some(self.tokens_per_owner_asset.get(&owner_asset_key(account_id, asset_id)))
+        }
+        if let Some(sku_id) = filter.sku_id.as_ref() {
+            return Some(self.tokens_per_sku.get(sku_id))
+        }
+        if let Some(license_id) = filter.license_id.as_ref() {
+            return Some(self.tokens_per_license.get(license_id))
+        }
+        if let Some(asset_id) = filter.asset_id.as_ref() {
+            return Some(self.tokens_per_asset.get(asset_id))
+        }
+        if let Some(account_id) = filter.account_id.as_ref() {
+            return Some(self.tokens_per_owner.get(account_id))
+        }
+        None
+    }
+
+    //scan the index from the cursor position, skip the first matching tokens and take up to limit of them
+    pub(crate) fn internal_filter_tokens(&self, filter: &FilterOpt, cursor: u64, skip: usize, limit: usize) -> TokensPage {
+        let index = self.internal_token_index(filter);
+        let token_ids = match index.as_ref() {
+            Some(Some(tokens_set)) => tokens_set.as_vector(),
+            Some(None) => return TokensPage{tokens: Vec::new(), next_cursor: None},
+            None => self.tokens_by_id.keys_as_vector(),
+        };
+
+        let now = env::block_timestamp_ms();
+        let mut tokens: Vec<LicenseToken> = Vec::new();
+        let mut skipped = 0;
+        let mut position = cursor;
+        while position < token_ids.len() && tokens.len() < limit {
+            let token = self.nft_token(token_ids.get(position).unwrap()).unwrap();
+            position += 1;
+            if !filter.matches(&token, now) {
+                continue
+            }
+            if skipped < skip {
+                skipped += 1;
+                continue
+            }
+            tokens.push(token);
+        }
+
+        TokensPage{
+            tokens,
+            next_cursor: if position < token_ids.len() { Some(U128(position as u128)) } else { None },
+        }
+    }
+}
@@ -18,6 +18,15 @@ pub enum EventLogVariant {
     NftProposeLicense(Vec<NftProposeLicenseLog>),
     NftApproveLicense(Vec<NftApproveLicenseLog>),
     NftRejectLicense(Vec<NftRejectLicenseLog>),
+    NftBurn(Vec<NftBurnLog>),
+    NftSuspendLicense(Vec<NftLicenseStatusLog>),
+    NftReinstateLicense(Vec<NftLicenseStatusLog>),
+    NftRevokeLicense(Vec<NftLicenseStatusLog>),
+    NftRenewLicense(Vec<NftRenewLicenseLog>),
+    NftUpdateSettings(Vec<NftUpdateSettingsLog>),
+    NftRevenueSplit(Vec<NftRevenueSplitLog>),
+    NftWithdrawProceeds(Vec<NftWithdrawProceedsLog>),
+    NftRefund(Vec<NftRefundLog>),
 }
 
 /// Interface to capture data about an event
@@ -131,6 +140,156 @@ pub struct NftRejectLicenseLog {
     pub memo: Option<String>,
 }
 
+/// An event log to capture token burning
+///
+/// Arguments
+/// * `owner_id`: owner of the burned tokens
+/// * `authorized_id`: account that burned the tokens on behalf of the owner
+/// * `token_ids`: ["1", "12345abc"]
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftBurnLog {
+    pub owner_id: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorized_id: Option<String>,
+
+    pub token_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture license status changes made by the licensor
+///
+/// Arguments
+/// * `owner_id`: owner of the tokens
+/// * `token_ids`: ["1", "12345abc"]
+/// * `reason`: why the status was changed
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftLicenseStatusLog {
+    pub owner_id: String,
+    pub token_ids: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture license renewal
+///
+/// Arguments
+/// * `owner_id`: owner of the tokens
+/// * `token_ids`: ["1", "12345abc"]
+/// * `expires_at`: new expiration, Unix epoch in milliseconds
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftRenewLicenseLog {
+    pub owner_id: String,
+    pub token_ids: Vec<String>,
+    pub expires_at: u64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture changes of the contract settings
+///
+/// Arguments
+/// * `owner_id`: contract owner
+/// * `slippage_percents`: default slippage of SKU prices
+/// * `storage_reserve`: part of the deposit kept for storage, in yoctoNEAR
+/// * `auto_payout`: whether the sale proceeds are transferred right away
+/// * `benefit_account_id`: optional receiver of the benefit fee
+/// * `benefit_fee_milli_percent`: optional benefit fee
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftUpdateSettingsLog {
+    pub owner_id: String,
+    pub slippage_percents: u32,
+    pub storage_reserve: U128,
+    pub auto_payout: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub benefit_account_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub benefit_fee_milli_percent: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture how the proceeds of a sale were paid out
+///
+/// Arguments
+/// * `ft_token_id`: the fungible token of the payment, NEAR if not set
+/// * `amount`: the paid amount
+/// * `benefit_fee`: part of the amount taken by the benefit account
+/// * `payees`: the rest of the amount split between the payees
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftRevenueSplitLog {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ft_token_id: Option<String>,
+    pub amount: U128,
+    pub benefit_fee: U128,
+    pub payees: Vec<NftRevenueShareLog>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftRevenueShareLog {
+    pub account_id: String,
+    pub amount: U128,
+}
+
+/// An event log to capture payouts of the sale proceeds
+///
+/// Arguments
+/// * `account_id`: receiver of the proceeds
+/// * `ft_token_id`: the fungible token of the proceeds, NEAR if not set
+/// * `amount`: the paid out amount
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftWithdrawProceedsLog {
+    pub account_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ft_token_id: Option<String>,
+    pub amount: U128,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event log to capture licenses returned within the refund window
+///
+/// Arguments
+/// * `owner_id`: the buyer who returned the licenses
+/// * `token_ids`: the burned tokens
+/// * `receipt_ids`: the refunded receipts
+/// * `memo`: optional message
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftRefundLog {
+    pub owner_id: String,
+    pub token_ids: Vec<String>,
+    pub receipt_ids: Vec<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,6 +347,93 @@ mod tests {
         };
         assert_eq!(expected, log.to_string());
     }
+
+    #[test]
+    fn nep_format_burn() {
+        let expected = r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_burn","data":[{"owner_id":"user1.near","authorized_id":"admin.near","token_ids":["token"]}]}"#;
+        let log = EventLog {
+            standard: "nep171".to_string(),
+            version: "1.0.0".to_string(),
+            event: EventLogVariant::NftBurn(vec![NftBurnLog {
+                owner_id: "user1.near".to_string(),
+                authorized_id: Some("admin.near".to_string()),
+                token_ids: vec!["token".to_string()],
+                memo: None,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_update_settings() {
+        let expected = r#"EVENT_JSON:{"standard":"nepTBD","version":"nftsentry-1.0.0","event":"nft_update_settings","data":[{"owner_id":"admin.near","slippage_percents":5,"storage_reserve":"100000000000000000000000","auto_payout":false,"benefit_account_id":"benefit.near","benefit_fee_milli_percent":2500}]}"#;
+        let log = EventLog {
+            standard: "nepTBD".to_string(),
+            version: "nftsentry-1.0.0".to_string(),
+            event: EventLogVariant::NftUpdateSettings(vec![NftUpdateSettingsLog {
+                owner_id: "admin.near".to_string(),
+                slippage_percents: 5,
+                storage_reserve: U128(100_000_000_000_000_000_000_000),
+                auto_payout: false,
+                benefit_account_id: Some("benefit.near".to_string()),
+                benefit_fee_milli_percent: Some(2500),
+                memo: None,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_revenue_split() {
+        let expected = r#"EVENT_JSON:{"standard":"nepTBD","version":"nftsentry-1.0.0","event":"nft_revenue_split","data":[{"amount":"1000","benefit_fee":"25","payees":[{"account_id":"alice.near","amount":"488"},{"account_id":"bob.near","amount":"487"}]}]}"#;
+        let log = EventLog {
+            standard: "nepTBD".to_string(),
+            version: "nftsentry-1.0.0".to_string(),
+            event: EventLogVariant::NftRevenueSplit(vec![NftRevenueSplitLog {
+                ft_token_id: None,
+                amount: U128(1000),
+                benefit_fee: U128(25),
+                payees: vec![
+                    NftRevenueShareLog { account_id: "alice.near".to_string(), amount: U128(488) },
+                    NftRevenueShareLog { account_id: "bob.near".to_string(), amount: U128(487) },
+                ],
+                memo: None,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_withdraw_proceeds() {
+        let expected = r#"EVENT_JSON:{"standard":"nepTBD","version":"nftsentry-1.0.0","event":"nft_withdraw_proceeds","data":[{"account_id":"alice.near","ft_token_id":"usdc.near","amount":"1500000"}]}"#;
+        let log = EventLog {
+            standard: "nepTBD".to_string(),
+            version: "nftsentry-1.0.0".to_string(),
+            event: EventLogVariant::NftWithdrawProceeds(vec![NftWithdrawProceedsLog {
+                account_id: "alice.near".to_string(),
+                ft_token_id: Some("usdc.near".to_string()),
+                amount: U128(1_500_000),
+                memo: None,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
+
+    #[test]
+    fn nep_format_refund() {
+        let expected = r#"EVENT_JSON:{"standard":"nepTBD","version":"nftsentry-1.0.0","event":"nft_refund","data":[{"owner_id":"bob.near","token_ids":["0"],"receipt_ids":[3,7]}]}"#;
+        let log = EventLog {
+            standard: "nepTBD".to_string(),
+            version: "nftsentry-1.0.0".to_string(),
+            event: EventLogVariant::NftRefund(vec![NftRefundLog {
+                owner_id: "bob.near".to_string(),
+                token_ids: vec!["0".to_string()],
+                receipt_ids: vec![3, 7],
+                memo: None,
+            }]),
+        };
+        assert_eq!(expected, log.to_string());
+    }
 }
 
 #[near_bindgen]
@@ -0,0 +1,386 @@
+use near_sdk::{Gas, PromiseError};
+use common_types::types::LicenseGeneral;
+use common_types::utils::format_balance;
+use crate::*;
+use crate::internal::*;
+
+const FT_MINT_METHOD: &str = "ft_on_transfer";
+pub(crate) const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+
+// A fungible token accepted as a payment for SKUs priced in its currency, e.g. "USD" for USDC.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtTokenConfig {
+    pub currency: String,
+    pub decimals: u8,
+}
+
+// Tokens received by ft_on_transfer.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtPayment {
+    pub ft_token_id: AccountId,
+    pub sender_id: AccountId,
+    pub amount: U128,
+}
+
+// The purchase paid by ft_transfer_call, passed in its msg.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum FtPaymentMsg {
+    Mint {
+        asset_id: String,
+        sku_id: Option<String>,
+        // the sender by default
+        receiver_id: Option<AccountId>,
+        token_id: Option<TokenId>,
+    },
+    UpdateLicense {
+        token_id: TokenId,
+        new_sku_id: String,
+    },
+    // adds the tokens to the refund escrow of the sender
+    RefundEscrow,
+}
+
+#[near_bindgen]
+impl Contract {
+    // Adds, updates or removes (config is None) an accepted fungible token.
+    pub fn set_ft_token(&mut self, token_account_id: AccountId, config: Option<FtTokenConfig>) {
+        if env::predecessor_account_id() != self.owner_id {
+            env::panic_str("Unauthorized")
+        }
+        match config {
+            Some(config) => self.ft_tokens.insert(&token_account_id, &config),
+            None => self.ft_tokens.remove(&token_account_id),
+        };
+    }
+
+    pub fn ft_tokens(&self) -> Vec<(AccountId, FtTokenConfig)> {
+        self.ft_tokens.to_vec()
+    }
+
+    // NEP-141 receiver. Returns the amount of tokens to refund to the sender: all of them
+    // if the purchase fails, the change otherwise. The storage of the purchase is taken off
+    // the NEAR storage balance of the sender, see deposit_storage.
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let payment = FtPayment{ft_token_id: env::predecessor_account_id(), sender_id: sender_id.clone(), amount};
+        if self.ft_tokens.get(&payment.ft_token_id).is_none() {
+            env::panic_str("Token is not accepted as a payment")
+        }
+        let payment_msg: FtPaymentMsg = near_sdk::serde_json::from_str(&msg).expect("Invalid payment message");
+        if !matches!(payment_msg, FtPaymentMsg::RefundEscrow) {
+            let storage_reserve = self.settings.storage_reserve.0;
+            if self.storage_balance_of(sender_id.clone()).0 < storage_reserve {
+                env::panic_str(&format!(
+                    "Storage balance must be at least {} NEAR, add it with deposit_storage",
+                    format_balance(storage_reserve),
+                ))
+            }
+        }
+
+        match payment_msg {
+            FtPaymentMsg::Mint { asset_id, sku_id, receiver_id, token_id } => {
+                let token_id = self.internal_mint_token_id(token_id);
+                let promise_meta: Promise = inventory_contract::ext(self.inventory_id.clone())
+                    .with_unused_gas_weight(4).inventory_metadata();
+                let promise_asset: Promise = inventory_contract::ext(self.inventory_id.clone())
+                    .with_unused_gas_weight(4).asset_token(asset_id);
+                PromiseOrValue::Promise(promise_meta.and(promise_asset).then(
+                    Self::ext(env::current_account_id())
+                        .with_unused_gas_weight(280)
+                        .on_ft_mint(
+                            payment, token_id, sku_id, receiver_id.unwrap_or(sender_id),
+                        )
+                ))
+            }
+            FtPaymentMsg::UpdateLicense { token_id, new_sku_id } => {
+                let token = self.nft_token(token_id.clone()).expect("Token does not exist");
+                if sender_id != token.owner_id {
+                    env::panic_str("License can only be updated directly by the token owner");
+                }
+                let (inventory_id, asset_id, _license_id, _sku) = token.inventory_asset_license_sku();
+                let inventory_account_id = AccountId::new_unchecked(inventory_id);
+                let promise_meta: Promise = inventory_contract::ext(inventory_account_id.clone())
+                    .with_unused_gas_weight(3).inventory_metadata();
+                let promise_asset: Promise = inventory_contract::ext(inventory_account_id.clone())
+                    .with_unused_gas_weight(3).asset_token(asset_id);
+                PromiseOrValue::Promise(promise_meta.and(promise_asset).then(
+                    Self::ext(env::current_account_id())
+                        .with_unused_gas_weight(15)
+                        .on_ft_license_update(payment, token_id, inventory_account_id, new_sku_id)
+                ))
+            }
+            FtPaymentMsg::RefundEscrow => {
+                self.internal_credit_refund_escrow(&sender_id, &Some(payment.ft_token_id), payment.amount.0);
+                PromiseOrValue::Value(U128(0))
+            }
+        }
+    }
+
+    #[private]
+    pub fn on_ft_mint(
+        &mut self,
+        #[callback_result] metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        #[callback_result] asset_res: Result<JsonAssetToken, PromiseError>,
+        payment: FtPayment,
+        token_id: TokenId,
+        sku_id: Option<String>,
+        receiver_id: AccountId,
+    ) -> PromiseOrValue<U128> {
+        let result = self.ensure_ft_mint(
+            metadata_res, asset_res, payment.clone(), token_id, sku_id, receiver_id,
+        );
+        result.unwrap_or_else(|msg| {
+            env::log_str(&format!("Error: {}", msg));
+            PromiseOrValue::Value(payment.amount)
+        })
+    }
+
+    fn ensure_ft_mint(
+        &mut self,
+        metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        asset_res: Result<JsonAssetToken, PromiseError>,
+        payment: FtPayment,
+        token_id: TokenId,
+        sku_id: Option<String>,
+        receiver_id: AccountId,
+    ) -> Result<PromiseOrValue<U128>, String> {
+        let inv_metadata = metadata_res.map_err(|_| "Failed call inventory_metadata".to_string())?;
+        let asset = asset_res.map_err(|_| "Failed call asset_token".to_string())?;
+        let config = self.ft_tokens.get(&payment.ft_token_id).ok_or("Token is not accepted as a payment".to_string())?;
+
+        let (asset_license, inv_license) = self.internal_mint_sku(
+            &asset, &inv_metadata, &sku_id, FT_MINT_METHOD,
+        )?;
+        let price = asset_license.get_ft_cost(&config.currency, config.decimals)?;
+        let payees = asset.revenue_payees(inv_metadata.metadata.revenue_split.as_ref(), inv_metadata.owner_id.clone());
+        let terms = SaleTerms::new(&asset_license, None, payees);
+        if payment.amount.0 < price {
+            return Err(format!(
+                "Transferred {} {} is less than SKU price of {} {}",
+                payment.amount.0, payment.ft_token_id, price, payment.ft_token_id,
+            ))
+        }
+
+        let lic_token = self.internal_issue_mint_license(
+            &asset, &inv_metadata, asset_license, inv_license, token_id, receiver_id,
+        )?;
+
+        if let Some(skip_policies) = self.metadata.get().unwrap().skip_policies {
+            if skip_policies {
+                return Ok(PromiseOrValue::Value(self.internal_ft_mint(
+                    lic_token, payment, price, terms,
+                )))
+            }
+        }
+        let full_inventory = self.get_full_inventory(
+            self.inventory_id.clone(), asset.clone(), inv_metadata.metadata.clone(),
+        );
+        let promise_new: Promise = policy_rules_contract::ext(self.policy_contract.clone())
+            .with_unused_gas_weight(100).check_new(
+            full_inventory,
+            lic_token.shrink(),
+            asset.policy_rules,
+            asset.upgrade_rules,
+        );
+        Ok(PromiseOrValue::Promise(promise_new.then(
+            Self::ext(env::current_account_id())
+                .with_unused_gas_weight(10)
+                .on_ft_check_new(lic_token, payment, U128(price), terms)
+        )))
+    }
+
+    #[private]
+    pub fn on_ft_check_new(
+        &mut self,
+        #[callback_result] check_new_res: Result<IsAvailableResponseData, PromiseError>,
+        lic_token: LicenseToken,
+        payment: FtPayment,
+        price: U128,
+        terms: SaleTerms,
+    ) -> U128 {
+        let error = match check_new_res {
+            Err(_) => Some("Failed call check_new()".to_string()),
+            Ok(res) if !res.result => Some(res.reason_not_available),
+            Ok(_) => None,
+        };
+        if let Some(msg) = error {
+            env::log_str(&format!("Error: {}", msg));
+            return payment.amount
+        }
+        self.internal_ft_mint(lic_token, payment, price.0, terms)
+    }
+
+    #[private]
+    pub fn on_ft_license_update(
+        &mut self,
+        #[callback_result] metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        #[callback_result] asset_res: Result<JsonAssetToken, PromiseError>,
+        payment: FtPayment,
+        token_id: TokenId,
+        inventory_id: AccountId,
+        new_sku_id: String,
+    ) -> PromiseOrValue<U128> {
+        let result = self.ensure_ft_update_license(
+            metadata_res, asset_res, payment.clone(), token_id, inventory_id, new_sku_id,
+        );
+        result.unwrap_or_else(|msg| {
+            env::log_str(&format!("Error: {}", msg));
+            PromiseOrValue::Value(payment.amount)
+        })
+    }
+
+    fn ensure_ft_update_license(
+        &self,
+        metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        asset_res: Result<JsonAssetToken, PromiseError>,
+        payment: FtPayment,
+        token_id: TokenId,
+        inventory_id: AccountId,
+        new_sku_id: String,
+    ) -> Result<PromiseOrValue<U128>, String> {
+        let inv_metadata = metadata_res.map_err(|_| "Failed call inventory_metadata".to_string())?;
+        let asset = asset_res.map_err(|_| "Failed call asset_token".to_string())?;
+        let config = self.ft_tokens.get(&payment.ft_token_id).ok_or("Token is not accepted as a payment".to_string())?;
+        let token = self.nft_token(token_id).ok_or("Token does not exist".to_string())?;
+
+        let find_sku = |sku_id: &String| asset.licenses.as_ref()
+            .and_then(|licenses| licenses.iter().find(|x| x.sku_id.as_ref() == Some(sku_id)))
+            .ok_or(format!("Asset license not found by sku_id {}", sku_id));
+        let new_asset_license = find_sku(&new_sku_id)?;
+        let old_asset_license = find_sku(&token.sku_id())?;
+
+        // Both SKUs are priced in the token currency
+        let price_diff = new_asset_license.get_ft_cost(&config.currency, config.decimals)?
+            .saturating_sub(old_asset_license.get_ft_cost(&config.currency, config.decimals)?);
+        if payment.amount.0 < price_diff {
+            return Err(format!(
+                "Transferred {} {} is less than license price difference of {} {}",
+                payment.amount.0, payment.ft_token_id, price_diff, payment.ft_token_id,
+            ))
+        }
+
+        let new_license = inv_metadata.metadata.licenses.iter().find(
+            |x| new_asset_license.license_id.as_ref() == Some(&x.license_id)).cloned();
+        let new_token = upgraded_license_token(
+            &asset, &token, new_license, new_asset_license.to_owned(), &inv_metadata.owner_id,
+        );
+        let payees = asset.revenue_payees(inv_metadata.metadata.revenue_split.as_ref(), inv_metadata.owner_id.clone());
+        let terms = SaleTerms::new(new_asset_license, None, payees);
+        let full_inventory = self.get_full_inventory(inventory_id, asset.clone(), inv_metadata.metadata);
+
+        let promise_transition: Promise = policy_rules_contract::ext(self.policy_contract.clone())
+            .with_unused_gas_weight(3).check_transition(
+            full_inventory,
+            token.shrink(),
+            new_token.shrink(),
+            asset.policy_rules.clone(),
+            asset.upgrade_rules.clone(),
+        );
+        Ok(PromiseOrValue::Promise(promise_transition.then(
+            Self::ext(env::current_account_id())
+                .with_unused_gas_weight(27)
+                .on_ft_check_transition(new_token, payment, U128(price_diff), terms)
+        )))
+    }
+
+    #[private]
+    pub fn on_ft_check_transition(
+        &mut self,
+        #[callback_result] check_transition_res: Result<Result<IsAvailableResponseData, String>, PromiseError>,
+        lic_token: LicenseToken,
+        payment: FtPayment,
+        price_diff: U128,
+        terms: SaleTerms,
+    ) -> U128 {
+        let error = match check_transition_res {
+            Err(_) => Some("Failed call check_transition()".to_string()),
+            Ok(Err(msg)) => Some(msg),
+            Ok(Ok(avail)) if !avail.result => Some(avail.reason_not_available),
+            Ok(Ok(_)) => None,
+        };
+        if let Some(msg) = error {
+            env::log_str(&format!("Error: {}", msg));
+            return payment.amount
+        }
+
+        let owner_id = lic_token.owner_id.clone();
+        let token = match self.tokens_by_id.get(&lic_token.token_id) {
+            Some(token) => token,
+            None => {
+                env::log_str("Error: Token does not exist");
+                return payment.amount
+            }
+        };
+        let initial_storage_usage = env::storage_usage();
+        self.internal_replace_license(&owner_id, &lic_token.token_id, lic_token.license.clone(), lic_token.metadata.clone());
+        let receipt_id = self.internal_record_sale(ReceiptKind::UpdateLicense, &lic_token, &payment.sender_id, terms, SalePayment {
+            ft_token_id: Some(payment.ft_token_id.clone()),
+            amount: price_diff.0,
+            refund: 0,
+        });
+        if let Err(msg) = self.internal_charge_storage(&payment.sender_id, initial_storage_usage) {
+            self.internal_rollback_sales(&[receipt_id]);
+            self.internal_rollback_replace_license(&token);
+            env::log_str(&format!("Error: {}", msg));
+            return payment.amount
+        }
+        self.internal_complete_sales(&[receipt_id], payment.amount.0 - price_diff.0);
+
+        let nft_update_license_log: EventLog = EventLog {
+            standard: NFT_LICENSE_STANDARD_NAME.to_string(),
+            version: NFT_LICENSE_SPEC.to_string(),
+            event: EventLogVariant::NftUpdateLicense(vec![NftUpdateLicenseLog {
+                owner_id: owner_id.to_string(),
+                token_ids: vec![lic_token.token_id],
+                memo: None,
+            }]),
+        };
+        self.log_event(&nft_update_license_log.to_string());
+
+        U128(payment.amount.0 - price_diff.0)
+    }
+}
+
+impl Contract {
+    fn internal_ft_mint(
+        &mut self,
+        lic_token: LicenseToken,
+        payment: FtPayment,
+        price: Balance,
+        terms: SaleTerms,
+    ) -> U128 {
+        let asset_id = lic_token.asset_id.clone();
+        let initial_storage_usage = env::storage_usage();
+        let mint_log = match self.internal_mint(lic_token.clone()) {
+            Ok(log) => log,
+            Err(msg) => {
+                env::log_str(&format!("Error: {}", msg));
+                return payment.amount
+            }
+        };
+        // Record the sale before charging the storage, so the buyer pays for its receipt too.
+        let receipt_id = self.internal_record_sale(ReceiptKind::Mint, &lic_token, &payment.sender_id, terms, SalePayment {
+            ft_token_id: Some(payment.ft_token_id.clone()),
+            amount: price,
+            refund: 0,
+        });
+        if let Err(msg) = self.internal_charge_storage(&payment.sender_id, initial_storage_usage) {
+            self.internal_rollback_sales(&[receipt_id]);
+            let token = self.tokens_by_id.get(&lic_token.token_id).unwrap();
+            self.internal_rollback_mint(&token);
+            env::log_str(&format!("Error: {}", msg));
+            return payment.amount
+        }
+
+        let license_sold = self.nft_token_supply_for_asset(asset_id.clone());
+        inventory_contract::ext(self.inventory_id.clone()).with_static_gas(Gas::ONE_TERA * 3).on_nft_mint(
+            asset_id, license_sold
+        );
+        self.internal_complete_sales(&[receipt_id], payment.amount.0 - price);
+
+        self.log_event(&mint_log.to_string());
+        U128(payment.amount.0 - price)
+    }
+}
@@ -0,0 +1,141 @@
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum TokenHistoryKind {
+    Mint,
+    Transfer,
+    TransferReverted,
+    LicenseReplaced,
+    LicenseRenewed,
+    LicenseStatusChanged,
+    Burn,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenHistoryEntry {
+    pub kind: TokenHistoryKind,
+    //owner of the token after the change
+    pub owner_id: AccountId,
+    //license held after the change, not set if the license didn't change
+    pub license: Option<TokenLicense>,
+    pub timestamp: u64, // Unix epoch in milliseconds
+    pub block_height: u64,
+}
+
+//indices of the first and the next history entry of the token
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Default)]
+pub struct TokenHistoryRange {
+    pub first: u64,
+    pub next: u64,
+}
+
+impl TokenHistoryRange {
+    pub fn len(&self) -> u64 {
+        self.next - self.first
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next == self.first
+    }
+}
+
+//owner and license terms of the token at some moment
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenHolder {
+    pub owner_id: AccountId,
+    pub license: Option<TokenLicense>,
+    pub since: u64, // Unix epoch in milliseconds
+}
+
+#[near_bindgen]
+impl Contract {
+    //get the number of history entries of the token
+    pub fn nft_token_history_length(&self, token_id: TokenId) -> u64 {
+        self.token_history.get(&token_id).map(|x| x.len()).unwrap_or(0)
+    }
+
+    //query the history of the token from the first entry using pagination
+    pub fn nft_token_history(&self, token_id: TokenId, from_index: Option<U128>, limit: Option<u64>) -> Vec<TokenHistoryEntry> {
+        let range = match self.token_history.get(&token_id) {
+            Some(range) => range,
+            None => return Vec::new(),
+        };
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        self.internal_token_history(&token_id, range)
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .collect()
+    }
+
+    //get who held the token and under which license terms at the given time
+    pub fn nft_token_holder_at(&self, token_id: TokenId, at: u64) -> Option<TokenHolder> {
+        let range = self.token_history.get(&token_id)?;
+        let mut holder: Option<TokenHolder> = None;
+        for entry in self.internal_token_history(&token_id, range) {
+            if entry.timestamp > at {
+                break
+            }
+            if entry.kind == TokenHistoryKind::Burn {
+                holder = None;
+                continue
+            }
+            let license = match entry.license {
+                Some(license) => Some(license),
+                None => holder.and_then(|x| x.license),
+            };
+            holder = Some(TokenHolder{owner_id: entry.owner_id, license, since: entry.timestamp});
+        }
+        holder
+    }
+}
+
+impl Contract {
+    fn internal_token_history<'a>(&'a self, token_id: &'a TokenId, range: TokenHistoryRange) -> impl Iterator<Item = TokenHistoryEntry> + 'a {
+        (range.first..range.next).filter_map(move |index| self.token_history_entries.get(&(token_id.clone(), index)))
+    }
+
+    //append an entry to the history of the token, the history is never truncated
+    //so the caller charges its storage to the account making the change
+    pub(crate) fn internal_add_token_history(
+        &mut self,
+        token_id: &TokenId,
+        kind: TokenHistoryKind,
+        owner_id: &AccountId,
+        license: Option<TokenLicense>,
+    ) {
+        let mut range = self.token_history.get(token_id).unwrap_or_default();
+        self.token_history_entries.insert(&(token_id.clone(), range.next), &TokenHistoryEntry{
+            kind,
+            owner_id: owner_id.clone(),
+            license,
+            timestamp: env::block_timestamp_ms(),
+            block_height: env::block_height(),
+        });
+        range.next += 1;
+        self.token_history.insert(token_id, &range);
+    }
+
+    //drop the entry added by a failed sale of the given kind, the earlier history of the token is kept
+    pub(crate) fn internal_rollback_token_history(&mut self, token_id: &TokenId, kind: TokenHistoryKind) {
+        let mut range = match self.token_history.get(token_id) {
+            Some(range) if !range.is_empty() => range,
+            _ => return,
+        };
+        let last = (token_id.clone(), range.next - 1);
+        if self.token_history_entries.get(&last).map(|x| x.kind) != Some(kind) {
+            return
+        }
+        self.token_history_entries.remove(&last);
+        range.next -= 1;
+        if range.is_empty() {
+            self.token_history.remove(token_id);
+        } else {
+            self.token_history.insert(token_id, &range);
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use crate::*;
 use near_sdk::{CryptoHash};
+use common_types::types::AssetLicense;
 use std::mem::size_of;
 
 //convert the royalty percentage and amount to pay into a payout (U128)
-// pub(crate) fn royalty_to_payout(royalty_percentage: u32, amount_to_pay: Balance) -> U128 {
-//     U128(royalty_percentage as u128 * amount_to_pay / 10_000u128)
-// }
+pub(crate) fn royalty_to_payout(royalty_percentage: u32, amount_to_pay: Balance) -> U128 {
+    U128(royalty_percentage as u128 * amount_to_pay / 10_000u128)
+}
 
 //calculate how many bytes the account ID is taking up
 pub(crate) fn bytes_for_approved_account_id(account_id: &AccountId) -> u64 {
@@ -36,6 +37,46 @@ pub(crate) fn refund_approved_account_ids(
     refund_approved_account_ids_iter(account_id, approved_account_ids.keys())
 }
 
+//get the transfer rule of the token license, free transfer for tokens without license
+pub(crate) fn token_transferability(token: &Token) -> Transferability {
+    token.license.as_ref().map(|x| x.transferability()).unwrap_or_default()
+}
+
+//fill the transfer fee receiver of the license if the rule doesn't set it
+pub(crate) fn seed_transfer_fee_receiver(lic_token: &mut LicenseToken, fee_receiver_id: &AccountId) {
+    if let Some(license) = lic_token.license.as_mut() {
+        if let Some(Transferability::WithFee { receiver_id, .. }) = license.metadata.transferability.as_mut() {
+            if receiver_id.is_none() {
+                *receiver_id = Some(fee_receiver_id.clone());
+            }
+        }
+    }
+}
+
+//issue the replacement license of the token, the fee receiver is kept from the previous license,
+//the licensor by default
+pub(crate) fn upgraded_license_token(
+    asset: &JsonAssetToken, token: &LicenseToken, new_license: Option<InventoryLicense>, new_asset_license: AssetLicense,
+    licensor_id: &AccountId,
+) -> LicenseToken {
+    let mut new_token = asset.issue_new_license(new_license, new_asset_license, token.token_id.clone());
+    new_token.owner_id = token.owner_id.clone();
+    let fee_receiver_id = token.license.as_ref()
+        .and_then(|x| x.transferability().fee())
+        .and_then(|(receiver_id, _)| receiver_id)
+        .unwrap_or_else(|| licensor_id.clone());
+    seed_transfer_fee_receiver(&mut new_token, &fee_receiver_id);
+    new_token
+}
+
+//pays the held license transfer fee to its receiver, or back to the payer if the transfer was reverted
+pub(crate) fn settle_transfer_fee(transfer_fee: Option<TransferFee>, transferred: bool) {
+    if let Some(fee) = transfer_fee {
+        let account_id = if transferred { fee.receiver_id } else { fee.payer_id };
+        Promise::new(account_id).transfer(fee.amount.0);
+    }
+}
+
 // used to generate a unique prefix in our storage collections (this is to avoid data collisions)
 pub(crate) fn hash_id(s: &String) -> CryptoHash {
     //get the default hash
@@ -45,7 +86,82 @@ pub(crate) fn hash_id(s: &String) -> CryptoHash {
     hash
 }
 
+//key of the owner+asset index
+pub(crate) fn owner_asset_key(owner_id: &AccountId, asset_id: &String) -> String {
+    format!("{}:{}", owner_id, asset_id)
+}
+
+//add the token ID to the set stored under the key of the index
+fn index_insert(index: &mut LookupMap<String, UnorderedSet<TokenId>>, key: &String, token_id: &TokenId, prefix: StorageKey) {
+    let mut tokens_set = index.get(key).unwrap_or_else(|| UnorderedSet::new(prefix.try_to_vec().unwrap()));
+    tokens_set.insert(token_id);
+    index.insert(key, &tokens_set);
+}
+
+//remove the token ID from the set stored under the key of the index
+fn index_remove(index: &mut LookupMap<String, UnorderedSet<TokenId>>, key: &String, token_id: &TokenId) {
+    if let Some(mut tokens_set) = index.get(key) {
+        tokens_set.remove(token_id);
+        if tokens_set.is_empty() {
+            index.remove(key);
+        } else {
+            index.insert(key, &tokens_set);
+        }
+    }
+}
+
 impl Contract {
+    //add the token to the owner+asset, SKU and license indices
+    pub(crate) fn internal_index_token(&mut self, token: &Token) {
+        let key = owner_asset_key(&token.owner_id, &token.asset_id);
+        let prefix = StorageKey::TokensPerOwnerAssetInner { key_hash: hash_id(&key) };
+        index_insert(&mut self.tokens_per_owner_asset, &key, &token.token_id, prefix);
+
+        if let Some(sku_id) = token.metadata.from.as_ref().and_then(|x| x.sku_id.clone()) {
+            let prefix = StorageKey::TokensPerSkuInner { sku_hash: hash_id(&sku_id) };
+            index_insert(&mut self.tokens_per_sku, &sku_id, &token.token_id, prefix);
+        }
+        if let Some(license) = token.license.as_ref() {
+            let prefix = StorageKey::TokensPerLicenseInner { license_hash: hash_id(&license.id) };
+            index_insert(&mut self.tokens_per_license, &license.id, &token.token_id, prefix);
+        }
+    }
+
+    //remove the token from the owner+asset, SKU and license indices
+    pub(crate) fn internal_unindex_token(&mut self, token: &Token) {
+        let key = owner_asset_key(&token.owner_id, &token.asset_id);
+        index_remove(&mut self.tokens_per_owner_asset, &key, &token.token_id);
+
+        if let Some(sku_id) = token.metadata.from.as_ref().and_then(|x| x.sku_id.clone()) {
+            index_remove(&mut self.tokens_per_sku, &sku_id, &token.token_id);
+        }
+        if let Some(license) = token.license.as_ref() {
+            index_remove(&mut self.tokens_per_license, &license.id, &token.token_id);
+        }
+    }
+
+    //reserve the next free numeric token ID
+    pub(crate) fn internal_next_token_id(&mut self) -> TokenId {
+        loop {
+            let token_id = self.next_token_id.to_string();
+            self.next_token_id = self.next_token_id.checked_add(1)
+                .unwrap_or_else(|| env::panic_str("No numeric token IDs left"));
+            if self.tokens_by_id.get(&token_id).is_none() {
+                return token_id
+            }
+        }
+    }
+
+    //keep the counter ahead of explicitly named (e.g. restored) numeric tokens
+    pub(crate) fn internal_bump_token_id(&mut self, token_id: &TokenId) {
+        //the largest ID has no next one, the counter is left as is
+        if let Some(next) = token_id.parse::<u64>().ok().and_then(|id| id.checked_add(1)) {
+            if next > self.next_token_id {
+                self.next_token_id = next;
+            }
+        }
+    }
+
     //add a token to the set of tokens an owner has
     pub(crate) fn internal_add_token_to_owner(
         &mut self,
@@ -129,6 +245,40 @@ impl Contract {
         }
     }
 
+    //license transfer fee of the token with the account receiving it, licenses are issued with
+    //the licensor as the receiver, older ones without it pay the inventory
+    pub(crate) fn transfer_fee(&self, token: &Token) -> Option<(AccountId, Balance)> {
+        token_transferability(token).fee().map(
+            |(receiver_id, fee)| (receiver_id.unwrap_or_else(|| self.inventory_id.clone()), fee)
+        )
+    }
+
+    //requires exactly 1 yoctoNEAR for a free transfer, otherwise the license transfer fee which is
+    //held until the transfer is settled, the rest of the deposit is refunded
+    pub(crate) fn internal_take_transfer_fee(&self, token_id: &TokenId) -> Option<TransferFee> {
+        let token = self.tokens_by_id.get(token_id).expect("No token");
+        match self.transfer_fee(&token) {
+            Some((receiver_id, fee)) => {
+                let deposit = env::attached_deposit();
+                if deposit < fee {
+                    env::panic_str(&format!(
+                        "Attached deposit of {} yoctoNEAR is less than license transfer fee of {} yoctoNEAR",
+                        deposit, fee,
+                    ));
+                }
+                let payer_id = env::predecessor_account_id();
+                if deposit > fee {
+                    Promise::new(payer_id.clone()).transfer(deposit - fee);
+                }
+                Some(TransferFee{receiver_id, payer_id, amount: U128(fee)})
+            }
+            None => {
+                assert_one_yocto();
+                None
+            }
+        }
+    }
+
     //transfers the NFT to the receiver_id (internal method and can't be called directly via CLI).
     pub(crate) fn internal_transfer(
         &mut self,
@@ -145,28 +295,33 @@ impl Contract {
         //if the sender doesn't equal the owner, we check if the sender is in the approval list
 		if sender_id != &token.owner_id {
 			//if the token's approved account IDs doesn't contain the sender, we panic
-			// if !token.approved_account_ids.contains_key(sender_id) {
+			if !token.approved_account_ids.contains_key(sender_id) {
 				env::panic_str("Unauthorized");
-			// }
+			}
 
 			// If they included an approval_id, check if the sender's actual approval_id is the same as the one included
-			// if let Some(enforced_approval_id) = approval_id {
-            //     //get the actual approval ID
-			// 	let actual_approval_id = token
-			// 		.approved_account_ids
-			// 		.get(sender_id)
-            //         //if the sender isn't in the map, we panic
-			// 		.expect("Sender is not approved account");
-            //
-            //     //make sure that the actual approval ID is the same as the one provided
-            //     assert_eq!(
-			// 		actual_approval_id, &enforced_approval_id,
-			// 		"The actual approval_id {} is different from the given approval_id {}",
-			// 		actual_approval_id, enforced_approval_id,
-			// 	);
-			// }
+			if let Some(enforced_approval_id) = approval_id {
+                //get the actual approval ID
+				let actual_approval_id = token
+					.approved_account_ids
+					.get(sender_id)
+                    //if the sender isn't in the map, we panic
+					.expect("Sender is not approved account");
+
+                //make sure that the actual approval ID is the same as the one provided
+                assert_eq!(
+					actual_approval_id, &enforced_approval_id,
+					"The actual approval_id {} is different from the given approval_id {}",
+					actual_approval_id, enforced_approval_id,
+				);
+			}
 		}
 
+        //we make sure that the license allows the transfer to the receiver
+        if let Err(msg) = token_transferability(&token).check_receiver(receiver_id) {
+            env::panic_str(&msg);
+        }
+
         //we make sure that the sender isn't sending the token to themselves
         assert_ne!(
             &token.owner_id, receiver_id,
@@ -184,15 +339,18 @@ impl Contract {
             owner_id: receiver_id.clone(),
             asset_id: token.asset_id.clone(),
             //reset the approval account IDs
-            // approved_account_ids: Default::default(),
-            // next_approval_id: token.next_approval_id,
+            approved_account_ids: Default::default(),
+            next_approval_id: token.next_approval_id,
             license: token.license.clone(),
             metadata: token.metadata.clone(),
             //we copy over the royalties from the previous token
-            // royalty: token.royalty.clone(),
+            royalty: token.royalty.clone(),
         };
         //insert that new token into the tokens_by_id, replacing the old entry 
         self.tokens_by_id.insert(token_id, &new_token);
+        self.internal_unindex_token(&token);
+        self.internal_index_token(&new_token);
+        self.internal_add_token_history(token_id, TokenHistoryKind::Transfer, receiver_id, None);
 
         //if there was some memo attached, we log it. 
         if let Some(memo) = memo.as_ref() {
@@ -233,4 +391,45 @@ impl Contract {
         //return the preivous token object that was transferred.
         token
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn setup_contract() -> Contract {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(0)).build());
+        Contract::new_default_meta(accounts(0), accounts(1))
+    }
+
+    #[test]
+    fn test_bump_token_id() {
+        let mut contract = setup_contract();
+        contract.internal_bump_token_id(&"not-a-number".to_string());
+        assert_eq!(contract.next_token_id, 1);
+        contract.internal_bump_token_id(&"41".to_string());
+        assert_eq!(contract.next_token_id, 42);
+        contract.internal_bump_token_id(&"7".to_string());
+        assert_eq!(contract.next_token_id, 42);
+        assert_eq!(contract.internal_next_token_id(), "42");
+
+        //the largest ID doesn't overflow the counter
+        contract.internal_bump_token_id(&u64::MAX.to_string());
+        assert_eq!(contract.next_token_id, 43);
+        assert_eq!(contract.internal_next_token_id(), "43");
+
+        contract.internal_bump_token_id(&(u64::MAX - 1).to_string());
+        assert_eq!(contract.next_token_id, u64::MAX);
+    }
+
+    #[test]
+    fn test_explicit_token_id_is_not_generated() {
+        let mut contract = setup_contract();
+        assert_eq!(contract.internal_mint_token_id(None), "1");
+        assert_eq!(contract.internal_mint_token_id(Some("5".to_string())), "5");
+        assert_eq!(contract.internal_mint_token_id(None), "6");
+        assert_eq!(contract.internal_mint_token_id(Some("custom".to_string())), "custom");
+        assert_eq!(contract.internal_mint_token_id(None), "7");
+    }
+}
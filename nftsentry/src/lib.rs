@@ -1,35 +1,55 @@
 use std::collections::HashMap;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedMap, UnorderedSet, Vector};
 use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{CryptoHash, PanicOnDefault, Promise, PromiseOrValue};
 use near_sdk::{env, near_bindgen, ext_contract, AccountId, Balance};
 pub use common_types::policy::{AllPolicies, IsAvailableResponseData, LimitationData, PolicyData};
 pub use common_types::types::{NFTContractMetadata, Token, TokenLicense, TokenMetadata};
-pub use common_types::types::{LicenseToken, FilterOpt};
+pub use common_types::types::{LicenseToken, FilterOpt, LicenseStatus, Transferability};
 pub use common_types::utils::*;
 pub use common_types::types::{InventoryLicense, JsonAssetToken, SKUAvailability};
 pub use common_types::types::{ExtendedInventoryMetadata, FullInventory, InventoryContractMetadata};
 use common_types::types::ShrinkedLicenseToken;
+use common_types::prices::{FallbackPrice, OracleConfig};
 
 pub use crate::metadata::*;
 pub use crate::mint::*;
+pub use crate::mint_batch::*;
+pub use crate::ft_payment::*;
+pub use crate::settings::*;
 pub use crate::nft_core::*;
 pub use crate::approval::*;
 pub use crate::royalty::*;
 pub use crate::events::*;
 pub use crate::license::*;
+pub use crate::history::*;
+pub use crate::receipts::*;
+pub use crate::quote::*;
 
 mod internal;
 pub mod approval;
 mod enumeration; 
 pub mod metadata;
 pub mod mint;
+pub mod mint_batch;
+pub mod ft_payment;
+pub mod oracle;
+pub mod settings;
+mod revenue;
+pub mod proceeds;
+pub mod receipts;
+pub mod refund;
+pub mod storage;
+pub mod quote;
 pub mod nft_core;
 mod royalty; 
 mod events;
 pub mod license;
+pub mod burn;
+pub mod verify;
+pub mod history;
 mod tests;
 
 /// This spec can be treated like a version of the standard.
@@ -47,6 +67,8 @@ pub const MAX_LIMIT: u64 = 1_000_000;
 pub struct BenefitConfig {
     account_id: AccountId,
     fee_milli_percent_amount: u32,
+    // perpetual royalty on secondary sales, in basis points
+    royalty: Option<u32>,
 }
 
 #[near_bindgen]
@@ -57,14 +79,45 @@ pub struct Contract {
     pub inventory_id: AccountId,
     pub policy_contract: AccountId,
     pub benefit_config: Option<BenefitConfig>,
+    pub settings: ContractSettings,
+    //price oracle and the owner-set price used when it is down
+    pub oracle_config: OracleConfig,
+    pub fallback_price: Option<FallbackPrice>,
+    //fungible tokens accepted as a payment, by token contract
+    pub ft_tokens: UnorderedMap<AccountId, FtTokenConfig>,
+    //claimable sale proceeds, by account and fungible token (NEAR if none)
+    pub proceeds: LookupMap<(AccountId, Option<AccountId>), Balance>,
+    //purchase receipts, receipt IDs are their indexes
+    pub receipts: Vector<Receipt>,
+    //receipt IDs by token ID, buyer, asset ID and inventory
+    pub receipts_per_token: LookupMap<TokenId, Vector<u64>>,
+    pub receipts_per_buyer: LookupMap<String, Vector<u64>>,
+    pub receipts_per_asset: LookupMap<String, Vector<u64>>,
+    pub receipts_per_inventory: LookupMap<String, Vector<u64>>,
+    //funds kept by payees to cover refunds of proceeds already paid out, by account and fungible token
+    pub refund_escrow: LookupMap<(AccountId, Option<AccountId>), Balance>,
+    //NEAR paying the storage of the purchases in fungible tokens, by buyer
+    pub storage_deposits: LookupMap<AccountId, Balance>,
 
     //keeps track of all the token IDs for a given account
     pub tokens_per_owner: LookupMap<AccountId, UnorderedSet<TokenId>>,
     pub tokens_per_asset: LookupMap<String, UnorderedSet<TokenId>>,
+    //secondary indices, keyed by "owner:asset", SKU ID and license ID
+    pub tokens_per_owner_asset: LookupMap<String, UnorderedSet<TokenId>>,
+    pub tokens_per_sku: LookupMap<String, UnorderedSet<TokenId>>,
+    pub tokens_per_license: LookupMap<String, UnorderedSet<TokenId>>,
 
     //keeps track of the token struct for a given token ID
     pub tokens_by_id: UnorderedMap<TokenId, Token>,
 
+    //numeric ID of the next contract-generated token
+    pub next_token_id: u64,
+
+    //keeps track of the ownership and license changes for a given token ID,
+    //the range of the token entries kept in token_history_entries
+    pub token_history: LookupMap<TokenId, TokenHistoryRange>,
+    pub token_history_entries: LookupMap<(TokenId, u64), TokenHistoryEntry>,
+
     //keeps track of the token metadata for a given token ID
     // pub token_metadata_by_id: UnorderedMap<TokenId, TokenMetadata>,
 
@@ -86,6 +139,11 @@ pub trait InventoryContract {
     fn asset_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<JsonAssetToken>;
 }
 
+#[ext_contract(ft_contract)]
+pub trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
 #[ext_contract(policy_rules_contract)]
 pub trait PolicyRulesContract {
     fn check_transition(
@@ -112,6 +170,27 @@ pub enum StorageKey {
     TokensPerType,
     TokensPerTypeInner { token_type_hash: CryptoHash },
     TokenTypesLocked,
+    TokenHistory,
+    TokenHistoryEntries,
+    TokensPerOwnerAsset,
+    TokensPerOwnerAssetInner { key_hash: CryptoHash },
+    TokensPerSku,
+    TokensPerSkuInner { sku_hash: CryptoHash },
+    TokensPerLicense,
+    TokensPerLicenseInner { license_hash: CryptoHash },
+    FtTokens,
+    Proceeds,
+    Receipts,
+    ReceiptsPerBuyer,
+    ReceiptsPerBuyerInner { account_id_hash: CryptoHash },
+    ReceiptsPerAsset,
+    ReceiptsPerAssetInner { asset_hash: CryptoHash },
+    ReceiptsPerInventory,
+    ReceiptsPerInventoryInner { inventory_hash: CryptoHash },
+    ReceiptsPerToken,
+    ReceiptsPerTokenInner { token_id_hash: CryptoHash },
+    RefundEscrow,
+    StorageDeposits,
 }
 
 #[near_bindgen]
@@ -155,7 +234,13 @@ impl Contract {
             //Storage keys are simply the prefixes used for the collections. This helps avoid data collision
             tokens_per_owner: LookupMap::new(StorageKey::TokensPerOwner.try_to_vec().unwrap()),
             tokens_per_asset: LookupMap::new(StorageKey::TokensPerAsset.try_to_vec().unwrap()),
+            tokens_per_owner_asset: LookupMap::new(StorageKey::TokensPerOwnerAsset.try_to_vec().unwrap()),
+            tokens_per_sku: LookupMap::new(StorageKey::TokensPerSku.try_to_vec().unwrap()),
+            tokens_per_license: LookupMap::new(StorageKey::TokensPerLicense.try_to_vec().unwrap()),
             tokens_by_id: UnorderedMap::new(StorageKey::TokensById.try_to_vec().unwrap()),
+            next_token_id: 1,
+            token_history: LookupMap::new(StorageKey::TokenHistory.try_to_vec().unwrap()),
+            token_history_entries: LookupMap::new(StorageKey::TokenHistoryEntries.try_to_vec().unwrap()),
             // token_metadata_by_id: UnorderedMap::new(
             //     StorageKey::TokenMetadataById.try_to_vec().unwrap(),
             // ),
@@ -174,6 +259,18 @@ impl Contract {
             ),
             policy_contract: policy_contract.unwrap_or(AccountId::new_unchecked("policies.rocketscience.testnet".to_string())),
             benefit_config,
+            ft_tokens: UnorderedMap::new(StorageKey::FtTokens.try_to_vec().unwrap()),
+            proceeds: LookupMap::new(StorageKey::Proceeds.try_to_vec().unwrap()),
+            receipts: Vector::new(StorageKey::Receipts.try_to_vec().unwrap()),
+            receipts_per_token: LookupMap::new(StorageKey::ReceiptsPerToken.try_to_vec().unwrap()),
+            receipts_per_buyer: LookupMap::new(StorageKey::ReceiptsPerBuyer.try_to_vec().unwrap()),
+            receipts_per_asset: LookupMap::new(StorageKey::ReceiptsPerAsset.try_to_vec().unwrap()),
+            receipts_per_inventory: LookupMap::new(StorageKey::ReceiptsPerInventory.try_to_vec().unwrap()),
+            refund_escrow: LookupMap::new(StorageKey::RefundEscrow.try_to_vec().unwrap()),
+            storage_deposits: LookupMap::new(StorageKey::StorageDeposits.try_to_vec().unwrap()),
+            settings: ContractSettings::default(),
+            oracle_config: OracleConfig::default(),
+            fallback_price: None,
         };
 
         //return the Contract object
@@ -1,7 +1,8 @@
 use crate::*;
+use crate::internal::*;
 
 use near_sdk::{PromiseError};
-use common_types::prices::{Asset, get_near_price};
+use common_types::prices::Asset;
 use common_types::types::{FullInventory, LicenseGeneral, NFTUpdateLicenseResult};
 use common_types::utils::{balance_from_string, format_balance};
 
@@ -38,7 +39,7 @@ impl Contract {
         let promise_asset: Promise = inventory_contract::ext(inventory_account_id.clone())
             .with_unused_gas_weight(3)
             .asset_token(asset_id);
-        let promise_price = get_near_price(3);
+        let promise_price = self.internal_get_near_price(3);
         let promise_inventory = promise_meta
             .and(promise_asset)
             .and(promise_price);
@@ -57,17 +58,17 @@ impl Contract {
     #[payable]
     pub fn on_license_update(
         &mut self,
-        #[callback_result] metadata_res: Result<InventoryContractMetadata, PromiseError>,
+        #[callback_result] metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
         #[callback_result] asset_res: Result<JsonAssetToken, PromiseError>,
         #[callback_result] price_res: Result<Option<Asset>, PromiseError>,
         token_id: TokenId,
-        _inventory_id: AccountId,
+        inventory_id: AccountId,
         predecessor_id: AccountId,
         new_sku_id: String,
     ) -> PromiseOrValue<NFTUpdateLicenseResult> {
         let result = self.ensure_update_license(
             metadata_res, asset_res, price_res,
-            token_id.clone(), new_sku_id, predecessor_id.clone(),
+            token_id.clone(), inventory_id, new_sku_id, predecessor_id.clone(),
         );
         if result.is_err() {
             let _ = refund_deposit(0, Some(predecessor_id), None);
@@ -83,10 +84,11 @@ impl Contract {
 
     fn ensure_update_license(
         &self,
-        metadata_res: Result<InventoryContractMetadata, PromiseError>,
+        metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
         asset_res: Result<JsonAssetToken, PromiseError>,
         price_res: Result<Option<Asset>, PromiseError>,
         token_id: TokenId,
+        inventory_id: AccountId,
         new_sku_id: String,
         predecessor_id: AccountId,
     ) -> Result<Promise, String> {
@@ -110,31 +112,25 @@ impl Contract {
 
         // Build full inventory for those.
         // First, populate licenses with actual prices from asset
-        let new_license = metadata.licenses.iter().find(
+        let new_license = metadata.metadata.licenses.iter().find(
             |x| new_asset_license.license_id.as_ref() == Some(&x.license_id)).cloned();
-        let full_inventory = self.get_full_inventory(asset.clone(), metadata);
-
-        let near_price_data = price_res.unwrap().unwrap();
-        let near_price = &near_price_data.reports.last().unwrap().price;
-        env::log_str(&format!("NEAR price: {} USD", near_price.string_price()));
+        let near_price = &self.internal_near_price(price_res)?;
+        let (terms, quote) = self.internal_quote_update(
+            &asset, &metadata, old_asset_license, new_asset_license, near_price,
+        );
+        let full_inventory = self.get_full_inventory(inventory_id, asset.clone(), metadata.metadata);
 
         // Check for valid deposit
-        let must_attach = balance_from_string(
-            new_asset_license.get_near_cost(near_price)
-        ) - balance_from_string(
-            old_asset_license.get_near_cost(near_price)
+        let must_attach = self.internal_charge_quote(&quote).ok_or(format!(
+            "Attached deposit of {} NEAR is less than license price difference of {} NEAR (with {}% slippage)",
+            format_balance(env::attached_deposit()),
+            format_balance(quote.price.0),
+            quote.slippage_percents,
+        ))?;
+
+        let new_token = upgraded_license_token(
+            &asset, &token, new_license, new_asset_license.to_owned(), &metadata.owner_id,
         );
-        let deposit = env::attached_deposit();
-        if deposit < must_attach {
-            return Err(format!(
-                "Attached deposit of {} NEAR is less than license price difference of {} NEAR",
-                format_balance(deposit),
-                format_balance(must_attach),
-            ))
-        }
-
-        let mut new_token: LicenseToken = asset.issue_new_license(new_license, new_asset_license.to_owned(), token_id.clone());
-        new_token.owner_id = token.owner_id.clone();
 
         let promise_transition: Promise = policy_rules_contract::ext(self.policy_contract.clone())
             .with_unused_gas_weight(3).check_transition(
@@ -151,6 +147,7 @@ impl Contract {
                 .on_check_transition_receiver(
                     new_token.clone(),
                     must_attach.clone(),
+                    terms,
                     predecessor_id.clone(),
                 )
         );
@@ -165,6 +162,7 @@ impl Contract {
         #[callback_result] check_transition_res: Result<Result<IsAvailableResponseData, String>, PromiseError>,
         lic_token: LicenseToken,
         price_diff: Balance,
+        terms: SaleTerms,
         predecessor_id: AccountId,
     ) -> NFTUpdateLicenseResult {
         let initial_storage_usage = env::storage_usage();
@@ -190,11 +188,10 @@ impl Contract {
         }
 
         //measure the initial storage being used on the contract
-        let inventory_id = lic_token.metadata.from.as_ref().unwrap().inventory_id.clone();
         let token = unsafe{self.nft_token(lic_token.token_id.clone()).unwrap_unchecked()};
-        let old_license = token.license.unwrap();
+        let old_token = self.tokens_by_id.get(&lic_token.token_id).unwrap();
 
-        self.internal_replace_license(&predecessor_id, &lic_token.token_id, lic_token.license);
+        self.internal_replace_license(&predecessor_id, &lic_token.token_id, lic_token.license.clone(), lic_token.metadata.clone());
 
         // Construct the mint log as per the events standard.
         let nft_update_license_log: EventLog = EventLog {
@@ -212,28 +209,28 @@ impl Contract {
             }]),
         };
 
-        //calculate the required storage which was the used - initial
-        let storage_usage = env::storage_usage();
-        if storage_usage > initial_storage_usage {
-            let result = refund_deposit(
-                storage_usage - initial_storage_usage,
-                Some(predecessor_id.clone()),
-                Some(price_diff)
-            );
-            if result.is_err() {
+        // Record the sale before measuring the storage, so the buyer pays for its receipt too.
+        let receipt_id = self.internal_record_sale(ReceiptKind::UpdateLicense, &lic_token, &predecessor_id, terms, SalePayment {
+            ft_token_id: None,
+            amount: price_diff,
+            refund: 0,
+        });
+        // refund the deposit left after the price difference and the storage, even if the storage didn't grow
+        let refund = match refund_storage(initial_storage_usage, Some(predecessor_id.clone()), Some(price_diff)) {
+            Ok(amount) => amount,
+            Err(msg) => {
                 // Refund failed due to storage costs.
                 // Rollback all changes!
-                self.internal_replace_license(&token.owner_id, &token.token_id, Some(old_license));
+                self.internal_rollback_sales(&[receipt_id]);
+                self.internal_rollback_replace_license(&old_token);
                 // Refund any deposit
                 let _ = refund_deposit(0, Some(predecessor_id), None);
 
-                let msg = result.unwrap_err();
                 env::log_str( &format!("Error: {}", msg));
                 return NFTUpdateLicenseResult{error: msg}
             }
-        }
-
-        self.process_fees(price_diff, AccountId::new_unchecked(inventory_id));
+        };
+        self.internal_complete_sales(&[receipt_id], refund);
 
         // Log the serialized json.
         self.log_event(&nft_update_license_log.to_string());
@@ -241,13 +238,202 @@ impl Contract {
         return NFTUpdateLicenseResult{error: String::new()}
     }
 
-    pub fn get_full_inventory(&self, asset: JsonAssetToken, metadata: InventoryContractMetadata) -> FullInventory {
+    // Renews a time-limited license for one more SKU duration at the actual SKU price.
+    #[payable]
+    pub fn nft_renew_license(&mut self, token_id: TokenId) -> Promise {
+        let predecessor_id = env::predecessor_account_id();
+        let token = self.nft_token(token_id.clone()).expect("Token does not exist");
+
+        if predecessor_id != token.owner_id {
+            env::panic_str("License can only be renewed directly by the token owner");
+        }
+        let (inventory_id, asset_id, _license_id, _sku) = token.inventory_asset_license_sku();
+        let inventory_account_id = AccountId::new_unchecked(inventory_id);
+
+        let promise_meta: Promise = inventory_contract::ext(inventory_account_id.clone())
+            .with_unused_gas_weight(3)
+            .inventory_metadata();
+        let promise_asset: Promise = inventory_contract::ext(inventory_account_id)
+            .with_unused_gas_weight(3)
+            .asset_token(asset_id);
+        let promise_price = self.internal_get_near_price(3);
+
+        promise_meta.and(promise_asset).and(promise_price).then(
+            Self::ext(env::current_account_id())
+                .with_attached_deposit(env::attached_deposit())
+                .with_unused_gas_weight(15)
+                .on_license_renew(token_id, predecessor_id)
+        )
+    }
+
+    #[private]
+    #[payable]
+    pub fn on_license_renew(
+        &mut self,
+        #[callback_result] metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        #[callback_result] asset_res: Result<JsonAssetToken, PromiseError>,
+        #[callback_result] price_res: Result<Option<Asset>, PromiseError>,
+        token_id: TokenId,
+        predecessor_id: AccountId,
+    ) -> PromiseOrValue<NFTUpdateLicenseResult> {
+        let result = self.ensure_renew_license(
+            metadata_res, asset_res, price_res, token_id, predecessor_id.clone(),
+        );
+        if let Err(msg) = result {
+            let _ = refund_deposit(0, Some(predecessor_id), None);
+            env::log_str(&format!("Error: {}", msg));
+            return PromiseOrValue::Value(NFTUpdateLicenseResult{error: msg})
+        }
+        result.unwrap()
+    }
+
+    fn ensure_renew_license(
+        &mut self,
+        metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        asset_res: Result<JsonAssetToken, PromiseError>,
+        price_res: Result<Option<Asset>, PromiseError>,
+        token_id: TokenId,
+        predecessor_id: AccountId,
+    ) -> Result<PromiseOrValue<NFTUpdateLicenseResult>, String> {
+        let metadata = metadata_res.map_err(|_| "Failed call inventory_metadata".to_string())?;
+        let asset = asset_res.map_err(|_| "Failed call asset_token".to_string())?;
+        let near_price = &self.internal_near_price(price_res)?;
+
+        let token = self.nft_token(token_id.clone()).ok_or("Token does not exist".to_string())?;
+        let stored_license = self.tokens_by_id.get(&token_id).and_then(|x| x.license)
+            .ok_or("Token has no license".to_string())?;
+        let status = renewable_status(&stored_license)?;
+        let sku_id = token.sku_id();
+        let asset_license = asset.licenses.as_ref()
+            .and_then(|licenses| licenses.iter().find(|x| x.sku_id.as_ref() == Some(&sku_id)))
+            .ok_or(format!("Asset license not found by sku_id {}", sku_id))?;
+
+        let license = &stored_license;
+        // An expired license is renewed from now, an active one from its expiration.
+        let now = env::block_timestamp_ms();
+        let expires_at = asset_license.expires_at(license.expires_at.unwrap_or(now).max(now))
+            .ok_or(format!("License of SKU {} is not time-limited", sku_id))?;
+
+        let price_str = asset_license.get_near_cost(near_price);
+        let price = balance_from_string(price_str.clone());
+        let slippage_percents = self.sku_slippage_percents(asset_license);
+        // the storage reserve covers the receipt of the renewal
+        let storage_reserve = self.settings.storage_reserve.0;
+        let price = self.internal_charge(price, minimum_price(price, slippage_percents), storage_reserve).ok_or(format!(
+            "Attached deposit of {} NEAR is less than SKU price of {} NEAR (with {}% slippage)",
+            format_balance(env::attached_deposit()),
+            price_str,
+            slippage_percents,
+        ))?;
+
+        let payees = asset.revenue_payees(metadata.metadata.revenue_split.as_ref(), metadata.owner_id.clone());
+        let terms = SaleTerms::new(asset_license, Some(near_price), payees);
+        if status == LicenseStatus::Active {
+            return Ok(PromiseOrValue::Value(
+                self.internal_renew_license(token_id, expires_at, price, terms, predecessor_id)
+            ))
+        }
+
+        // Expired licenses don't count against the asset limits, so their objects
+        // may have been licensed again meanwhile: check the renewed license as a new one.
+        let mut renewed = token.clone();
+        if let Some(license) = renewed.license.as_mut() {
+            license.expires_at = Some(expires_at);
+            license.status = Some(LicenseStatus::Active);
+        }
+        renewed.metadata.expires_at = Some(expires_at);
+        let (inventory_id, _asset_id, _license_id, _sku) = token.inventory_asset_license_sku();
+        let full_inventory = self.get_full_inventory(
+            AccountId::new_unchecked(inventory_id), asset.clone(), metadata.metadata,
+        );
+
+        let promise_new: Promise = policy_rules_contract::ext(self.policy_contract.clone())
+            .with_unused_gas_weight(3).check_new(
+            full_inventory,
+            renewed.shrink(),
+            asset.policy_rules,
+            asset.upgrade_rules,
+        );
+        Ok(PromiseOrValue::Promise(promise_new.then(
+            Self::ext(env::current_account_id())
+                .with_attached_deposit(env::attached_deposit())
+                .with_unused_gas_weight(10)
+                .on_check_renew_receiver(token_id, expires_at, price, terms, predecessor_id)
+        )))
+    }
+
+    #[private]
+    #[payable]
+    pub fn on_check_renew_receiver(
+        &mut self,
+        #[callback_result] check_new_res: Result<IsAvailableResponseData, PromiseError>,
+        token_id: TokenId,
+        expires_at: u64,
+        price: Balance,
+        terms: SaleTerms,
+        predecessor_id: AccountId,
+    ) -> NFTUpdateLicenseResult {
+        let error = match check_new_res {
+            Err(_) => "Failed call check_new()".to_string(),
+            Ok(res) if !res.result => res.reason_not_available,
+            Ok(_) => return self.internal_renew_license(token_id, expires_at, price, terms, predecessor_id),
+        };
+        let _ = refund_deposit(0, Some(predecessor_id), None);
+        env::log_str(&format!("Error: {}", error));
+        NFTUpdateLicenseResult{error}
+    }
+
+    // Suspends, reinstates or revokes the license of the token. Called by the licensor
+    // through the inventory contract or by the contract owner.
+    #[payable]
+    pub fn nft_set_license_status(&mut self, token_id: TokenId, status: LicenseStatus, reason: Option<String>) {
+        let initial_storage_usage = env::storage_usage();
+        let mut token = self.tokens_by_id.get(&token_id).expect("No token");
+
+        let predecessor_id = env::predecessor_account_id();
+        if predecessor_id != self.owner_id && predecessor_id != self.inventory_id {
+            env::panic_str("Only the licensor or the owner can change the license status")
+        }
+
+        let license = token.license.as_mut().expect("Token has no license");
+        if let Err(msg) = license.status().check_transition(status) {
+            env::panic_str(&msg)
+        }
+        license.status = Some(status);
+        license.status_reason = reason.clone();
+        license.updated_at = Some(env::block_timestamp_ms());
+        self.tokens_by_id.insert(&token_id, &token);
+        self.internal_add_token_history(&token_id, TokenHistoryKind::LicenseStatusChanged, &token.owner_id, token.license.clone());
+
+        let log = vec![NftLicenseStatusLog {
+            owner_id: token.owner_id.to_string(),
+            token_ids: vec![token_id],
+            reason,
+            memo: None,
+        }];
+        let nft_license_status_log: EventLog = EventLog {
+            standard: NFT_LICENSE_STANDARD_NAME.to_string(),
+            version: NFT_LICENSE_SPEC.to_string(),
+            event: match status {
+                LicenseStatus::Suspended => EventLogVariant::NftSuspendLicense(log),
+                LicenseStatus::Revoked => EventLogVariant::NftRevokeLicense(log),
+                _ => EventLogVariant::NftReinstateLicense(log),
+            },
+        };
+
+        let _ = refund_storage(initial_storage_usage, None, None);
+
+        self.log_event(&nft_license_status_log.to_string());
+    }
+
+    pub fn get_full_inventory(&self, inventory_id: AccountId, asset: JsonAssetToken, metadata: InventoryContractMetadata) -> FullInventory {
         // Build full inventory for those.
         // First, populate licenses with actual prices from asset
         let tokens = self.shrinked_nft_tokens_for_asset(
             asset.token_id.clone(),
         );
         let full_inventory = FullInventory{
+            inventory_id: Some(inventory_id),
             inventory_licenses: metadata.licenses,
             issued_licenses: tokens,
             asset: Some(asset),
@@ -450,12 +636,30 @@ impl Contract {
     //     }
     // }
 
+    // Puts the token back as it was before internal_replace_license, when the sale can't pay for the storage.
+    pub(crate) fn internal_rollback_replace_license(&mut self, token: &Token) {
+        let replaced = self.tokens_by_id.get(&token.token_id).expect("Token does not exist");
+        self.internal_unindex_token(&replaced);
+        self.tokens_by_id.insert(&token.token_id, token);
+        self.internal_index_token(token);
+        self.internal_rollback_token_history(&token.token_id, TokenHistoryKind::LicenseReplaced);
+    }
+
     #[private]
-    pub fn internal_replace_license(&mut self, account_id: &AccountId, token_id: &TokenId, license: Option<TokenLicense>) {
+    pub fn internal_replace_license(&mut self, account_id: &AccountId, token_id: &TokenId, license: Option<TokenLicense>, metadata: TokenMetadata) {
         println!("==>internal_replace_license, account={}", account_id);
         let mut token = self.tokens_by_id.get(&token_id).expect("Token does not exist");
+        self.internal_unindex_token(&token);
         token.license = license;
+        // the token now comes from the SKU of the license: its SKU index, expiration
+        // and refund window follow it
+        token.metadata.from = metadata.from;
+        token.metadata.sku_data = metadata.sku_data;
+        token.metadata.expires_at = metadata.expires_at;
+        token.metadata.updated_at = metadata.updated_at;
         self.tokens_by_id.insert(&token_id, &token);
+        self.internal_index_token(&token);
+        self.internal_add_token_history(token_id, TokenHistoryKind::LicenseReplaced, &token.owner_id, token.license.clone());
         // if let Some(_license) = self.token_license_by_id.get(&token_id) {
         //     self.token_license_by_id.remove(&token_id);
         //
@@ -497,3 +701,130 @@ impl Contract {
     // }
 }
 
+
+impl Contract {
+    // Extends the license of the token to expires_at and records the renewal sale.
+    fn internal_renew_license(
+        &mut self,
+        token_id: TokenId,
+        expires_at: u64,
+        price: Balance,
+        terms: SaleTerms,
+        predecessor_id: AccountId,
+    ) -> NFTUpdateLicenseResult {
+        let initial_storage_usage = env::storage_usage();
+        let old_token = match self.tokens_by_id.get(&token_id) {
+            Some(token) if token.license.is_some() => token,
+            _ => {
+                let _ = refund_deposit(0, Some(predecessor_id), None);
+                return NFTUpdateLicenseResult{error: "Token does not exist".to_string()}
+            }
+        };
+        let mut token = self.tokens_by_id.get(&token_id).unwrap();
+        let now = env::block_timestamp_ms();
+        if let Some(license) = token.license.as_mut() {
+            license.expires_at = Some(expires_at);
+            license.updated_at = Some(now);
+        }
+        token.metadata.expires_at = Some(expires_at);
+        token.metadata.updated_at = Some(now);
+        self.tokens_by_id.insert(&token_id, &token);
+        self.internal_add_token_history(&token_id, TokenHistoryKind::LicenseRenewed, &token.owner_id, token.license.clone());
+
+        // Record the sale before measuring the storage, so the buyer pays for its receipt and history entry too.
+        let lic_token = self.nft_token(token_id.clone()).unwrap();
+        let receipt_id = self.internal_record_sale(ReceiptKind::RenewLicense, &lic_token, &predecessor_id, terms, SalePayment {
+            ft_token_id: None,
+            amount: price,
+            refund: 0,
+        });
+        let refund = match refund_storage(initial_storage_usage, Some(predecessor_id.clone()), Some(price)) {
+            Ok(amount) => amount,
+            Err(msg) => {
+                // Rollback all changes!
+                self.internal_rollback_sales(&[receipt_id]);
+                self.tokens_by_id.insert(&token_id, &old_token);
+                self.internal_rollback_token_history(&token_id, TokenHistoryKind::LicenseRenewed);
+                let _ = refund_deposit(0, Some(predecessor_id), None);
+
+                env::log_str(&format!("Error: {}", msg));
+                return NFTUpdateLicenseResult{error: msg}
+            }
+        };
+        self.internal_complete_sales(&[receipt_id], refund);
+
+        let nft_renew_license_log: EventLog = EventLog {
+            standard: NFT_LICENSE_STANDARD_NAME.to_string(),
+            version: NFT_LICENSE_SPEC.to_string(),
+            event: EventLogVariant::NftRenewLicense(vec![NftRenewLicenseLog {
+                owner_id: token.owner_id.to_string(),
+                token_ids: vec![token_id],
+                expires_at,
+                memo: None,
+            }]),
+        };
+        self.log_event(&nft_renew_license_log.to_string());
+
+        NFTUpdateLicenseResult{error: String::new()}
+    }
+}
+
+// Status of a license that can be renewed: active, or expired while active. A license the
+// licensor suspended or revoked can't be renewed, even if it has expired meanwhile.
+fn renewable_status(license: &TokenLicense) -> Result<LicenseStatus, String> {
+    let stored = license.status.unwrap_or_default();
+    if stored != LicenseStatus::Active {
+        return Err(format!("License is {}", stored))
+    }
+    Ok(license.status())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_types::types::LicenseData;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn token_license(expires_at: u64, status: Option<LicenseStatus>) -> TokenLicense {
+        TokenLicense {
+            id: "license".to_string(),
+            title: None,
+            description: None,
+            uri: None,
+            metadata: LicenseData {
+                exclusivity: None,
+                personal_use: None,
+                commercial_use: None,
+                display_sublicensee: None,
+                hate_speech_termination: None,
+                creative_commons: None,
+                moral_use_restrictions: None,
+                template: None,
+                pdf_url: None,
+                version: None,
+                transferability: None,
+            },
+            issued_at: Some(0),
+            expires_at: Some(expires_at),
+            starts_at: Some(0),
+            updated_at: None,
+            status,
+            status_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_renewable_status() {
+        testing_env!(VMContextBuilder::new().block_timestamp(2_000 * 1_000_000).build());
+        assert_eq!(renewable_status(&token_license(3_000, None)), Ok(LicenseStatus::Active));
+        assert_eq!(renewable_status(&token_license(1_000, None)), Ok(LicenseStatus::Expired));
+        assert_eq!(renewable_status(&token_license(1_000, Some(LicenseStatus::Active))), Ok(LicenseStatus::Expired));
+        //suspended licenses past their expiration report expired, they still can't be renewed
+        let suspended = token_license(1_000, Some(LicenseStatus::Suspended));
+        assert_eq!(suspended.status(), LicenseStatus::Expired);
+        assert_eq!(renewable_status(&suspended), Err("License is suspended".to_string()));
+        assert_eq!(renewable_status(&token_license(3_000, Some(LicenseStatus::Suspended))), Err("License is suspended".to_string()));
+        assert_eq!(renewable_status(&token_license(1_000, Some(LicenseStatus::Revoked))), Err("License is revoked".to_string()));
+    }
+}
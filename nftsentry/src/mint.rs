@@ -1,11 +1,11 @@
 use near_sdk::{Gas, PromiseError};
-use common_types::prices::{Asset, get_near_price};
-use common_types::types::{NFTMintResult};
-use common_types::utils::{balance_from_string, format_balance};
+use common_types::prices::Asset;
+use common_types::types::{AssetLicense, NFTMintResult};
+use common_types::utils::{format_balance, mul_div_round};
 use crate::*;
+use crate::internal::*;
 
-const SLIPPAGE_PERCENTS: i32 = 3;
-const MINT_METHOD: &str = "nft_mint";
+pub(crate) const MINT_METHOD: &str = "nft_mint";
 const MINT_OWNER_METHOD: &str = "nft_mint_owner";
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
@@ -26,23 +26,19 @@ impl Contract {
     #[payable]
     pub fn nft_mint(
         &mut self,
-        token_id: TokenId,
+        token_id: Option<TokenId>,
         asset_id: String,
         sku_id: Option<String>,
         receiver_id: AccountId,
     ) -> Promise {
-        if self.tokens_by_id.get(&token_id).is_some() {
-            env::panic_str("Token already exists")
-            // let max_token = self.nft_token_id_max();
-            // token_id = (i64::from_str(&max_token).unwrap() + 1).to_string();
-        }
+        let token_id = self.internal_mint_token_id(token_id);
 
         // Schedule calls to metadata and asset token
         let promise_meta: Promise = inventory_contract::ext(self.inventory_id.clone())
             .with_unused_gas_weight(4).inventory_metadata();
         let promise_asset: Promise = inventory_contract::ext(self.inventory_id.clone())
             .with_unused_gas_weight(4).asset_token(asset_id.clone());
-        let promise_price = get_near_price(4);
+        let promise_price = self.internal_get_near_price(4);
         let promise_inventory = promise_meta
             .and(promise_asset)
             .and(promise_price);
@@ -64,7 +60,7 @@ impl Contract {
     #[payable]
     pub fn nft_mint_owner(
         &mut self,
-        token_id: TokenId,
+        token_id: Option<TokenId>,
         asset_id: String,
         sku_id: Option<String>,
         receiver_id: AccountId,
@@ -75,18 +71,14 @@ impl Contract {
             env::panic_str("Only the owner or inventory can call this method")
         }
 
-        if self.tokens_by_id.get(&token_id).is_some() {
-            env::panic_str("Token already exists")
-            // let max_token = self.nft_token_id_max();
-            // token_id = (i64::from_str(&max_token).unwrap() + 1).to_string();
-        }
+        let token_id = self.internal_mint_token_id(token_id);
 
         // Schedule calls to metadata and asset token
         let promise_meta: Promise = inventory_contract::ext(self.inventory_id.clone())
             .with_unused_gas_weight(4).inventory_metadata();
         let promise_asset: Promise = inventory_contract::ext(self.inventory_id.clone())
             .with_unused_gas_weight(4).asset_token(asset_id.clone());
-        let promise_price = get_near_price(4);
+        let promise_price = self.internal_get_near_price(4);
         let promise_inventory = promise_meta
             .and(promise_asset)
             .and(promise_price);
@@ -131,7 +123,9 @@ impl Contract {
                 env::log_str( &format!("Error: {}", msg));
                 // env::panic_str(result.unwrap_err_unchecked().as_str());
                 return PromiseOrValue::Value(NFTMintResult{
+                    token_id: Some(token_id),
                     license_token: None,
+                    receipt_id: None,
                     error: msg,
                 })
             }
@@ -153,67 +147,44 @@ impl Contract {
         ) -> Result<PromiseOrValue<NFTMintResult>, String> {
 
         // 1. Check callback results first.
-        if metadata_res.is_err() || asset_res.is_err() || price_res.is_err() {
+        if metadata_res.is_err() || asset_res.is_err() {
             return if metadata_res.is_err() {
                 Err("Failed call inventory_metadata".to_string())
-            } else if asset_res.is_err() {
-                Err("Failed call asset_token".to_string())
             } else {
-                Err("Failed call priceoracle.get_asset".to_string())
+                Err("Failed call asset_token".to_string())
             }
         }
+        let near_price = &self.internal_near_price(price_res)?;
 
         unsafe {
             let asset = asset_res.unwrap_unchecked();
             let inv_metadata = metadata_res.unwrap_unchecked();
 
-            let near_price_data = price_res.unwrap_unchecked().unwrap();
-            let near_price = &near_price_data.reports.last().unwrap().price;
-            env::log_str(&format!("NEAR price: {} USD", near_price.string_price()));
-
-            let asset_license_opt = asset.licenses.as_ref().unwrap().iter().find(
-                |x| x.sku_id == sku_id
-            );
-
-            if asset_license_opt.is_none() {
-                return Err(format!("Asset license not found by sku_id {}", sku_id.unwrap_or(String::new())))
-            }
-            let mut asset_license = (*asset_license_opt.unwrap()).clone();
+            let (mut asset_license, inv_license) = self.internal_mint_sku(
+                &asset, &inv_metadata, &sku_id, &opts.from_method,
+            )?;
 
-            if asset_license.hidden.unwrap_or(false) && opts.from_method == MINT_METHOD {
-                return Err(format!("Asset license could not be minted using this method."))
-            }
-
-            let inv_license = inv_metadata.metadata.licenses.iter().find(
-                |x| asset_license.license_id.is_some() && Some(&x.license_id) == asset_license.license_id.as_ref()
-            ).cloned();
+            let (terms, quote) = self.internal_quote_mint(&asset, &inv_metadata, &asset_license, near_price);
 
             // Re-calculate and re-assign a price
-            // let price_currency = asset_license.price.clone().unwrap();
             let price_str = asset_license.get_near_cost(near_price);
             asset_license.price = price_str.clone();
 
             let deposit = env::attached_deposit();
-            let storage_price = balance_from_string("0.1".to_string());
-            let mut price = balance_from_string(price_str.clone());
-
-            if deposit < price + storage_price && !opts.mint_opt.is_gift {
-                let reserved_price = deposit - storage_price;
-                let minimum_price = price * (100 - SLIPPAGE_PERCENTS) as u128 / 100;
-                if reserved_price < minimum_price {
-                    return Err(format!(
-                        "Attached deposit of {} NEAR is less than SKU price of {} NEAR (with {}% slippage)",
-                        format_balance(deposit),
-                        price_str,
-                        SLIPPAGE_PERCENTS,
-                    ))
-                }
-                price = reserved_price;
-
+            let mut price = quote.price.0;
+
+            if !opts.mint_opt.is_gift {
+                price = self.internal_charge_quote(&quote).ok_or(format!(
+                    "Attached deposit of {} NEAR is less than SKU price of {} NEAR (with {}% slippage)",
+                    format_balance(deposit),
+                    price_str,
+                    quote.slippage_percents,
+                ))?;
             }
 
-            let mut lic_token = asset.issue_new_license(inv_license, asset_license, token_id);
-            lic_token.owner_id = receiver_id;
+            let lic_token = self.internal_issue_mint_license(
+                &asset, &inv_metadata, asset_license, inv_license, token_id, receiver_id,
+            )?;
 
             if let Some(skip_policies) = self.metadata.get().unwrap().skip_policies {
                 if skip_policies {
@@ -221,13 +192,15 @@ impl Contract {
                         lic_token,
                         price,
                         asset.token_id,
-                        inv_metadata.owner_id,
+                        terms,
                         predecessor_id,
                         opts,
                     )))
                 }
             }
-            let full_inventory = self.get_full_inventory(asset.clone(), inv_metadata.metadata.clone());
+            let full_inventory = self.get_full_inventory(
+                self.inventory_id.clone(), asset.clone(), inv_metadata.metadata.clone(),
+            );
 
             let promise_new: Promise = policy_rules_contract::ext(self.policy_contract.clone())
                 .with_unused_gas_weight(100).check_new(
@@ -243,7 +216,7 @@ impl Contract {
                     .on_check_new_receiver(
                         lic_token,
                         price, asset.token_id,
-                        inv_metadata.owner_id,
+                        terms,
                         predecessor_id,
                         opts,
                     )
@@ -260,30 +233,18 @@ impl Contract {
         lic_token: LicenseToken,
         asset_license_price: Balance,
         asset_id: String,
-        inventory_owner: AccountId,
+        terms: SaleTerms,
         predecessor_id: AccountId,
         opts: OnMintOpt,
     ) -> NFTMintResult {
         // measure the initial storage being used on the contract
 
-        // we add an optional parameter for perpetual royalties
-        // create a royalty map to store in the token
-        // let mut royalty = HashMap::new();
-
-        // if perpetual royalties were passed into the function:
-        // if let Some(perpetual_royalties) = perpetual_royalties {
-        //make sure that the length of the perpetual royalties is below 7 since we won't have enough GAS to pay out that many people
-        // assert!(perpetual_royalties.len() < 7, "Cannot add more than 6 perpetual royalty amounts");
-
-        //iterate through the perpetual royalties and insert the account and amount in the royalty map
-        // for (account, amount) in perpetual_royalties {
-        //     royalty.insert(account, amount);
-        // }
-        // }
         if check_new_res.is_err() {
             let _ = refund_deposit(0, Some(predecessor_id.clone()), None);
             return NFTMintResult {
+                token_id: Some(lic_token.token_id),
                 license_token: None,
+                receipt_id: None,
                 error: "Failed call check_new()".to_string(),
             }
         } else {
@@ -291,13 +252,15 @@ impl Contract {
             if !res.result {
                 let _ = refund_deposit(0, Some(predecessor_id.clone()), None);
                 return NFTMintResult {
+                    token_id: Some(lic_token.token_id),
                     license_token: None,
+                    receipt_id: None,
                     error: res.reason_not_available,
                 }
             }
         }
         return self.on_check_actual_mint(
-            lic_token, asset_license_price, asset_id, inventory_owner, predecessor_id, opts
+            lic_token, asset_license_price, asset_id, terms, predecessor_id, opts
         )
     }
 
@@ -307,7 +270,7 @@ impl Contract {
         lic_token: LicenseToken,
         asset_license_price: Balance,
         asset_id: String,
-        inventory_owner: AccountId,
+        terms: SaleTerms,
         predecessor_id: AccountId,
         opts: OnMintOpt,
     ) -> NFTMintResult {
@@ -319,10 +282,12 @@ impl Contract {
             owner_id: lic_token.owner_id.clone(),
             asset_id: asset_id.clone(),
             //we set the approved account IDs to the default value (an empty map)
+            approved_account_ids: Default::default(),
+            next_approval_id: 0,
             license: lic_token.license.clone(),
             metadata: lic_token.metadata.clone(),
             //the map of perpetual royalties for the token (The owner will get 100% - total perpetual royalties)
-            // royalty: royalty.clone(),
+            royalty: lic_token.royalty.clone(),
         };
 
 
@@ -332,7 +297,7 @@ impl Contract {
             let _ = refund_deposit(0, Some(predecessor_id), None);
             let msg = unsafe{mint_result.unwrap_err_unchecked()};
             env::log_str( &format!("Error: {}", msg));
-            return NFTMintResult{license_token: None, error: msg.to_string()}
+            return NFTMintResult{token_id: Some(token.token_id), license_token: None, receipt_id: None, error: msg.to_string()}
         }
         // ----- Token mint end -----
 
@@ -342,63 +307,157 @@ impl Contract {
         } else {
             Some(asset_license_price.clone())
         };
+        // Record the sale before measuring the storage, so the buyer pays for its receipt too.
+        let receipt_id = if opts.mint_opt.is_gift {
+            None
+        } else {
+            Some(self.internal_record_sale(ReceiptKind::Mint, &lic_token, &predecessor_id, terms, SalePayment {
+                ft_token_id: None,
+                amount: asset_license_price,
+                refund: 0,
+            }))
+        };
         // refund any excess storage if the user attached too much.
         let result = refund_storage(
             initial_storage_usage,
             Some(predecessor_id.clone()),
             charged_price,
         );
-        if result.is_err() {
+        if let Err(msg) = result {
             // Refund failed due to storage costs.
             // Rollback all changes!
-            self.internal_remove_token_from_owner(&token.owner_id, &token.token_id);
-            self.internal_remove_token_from_asset(&token.asset_id, &token.token_id);
-            self.tokens_by_id.remove(&token.token_id);
+            if let Some(receipt_id) = receipt_id {
+                self.internal_rollback_sales(&[receipt_id]);
+            }
+            self.internal_rollback_mint(&token);
             // self.token_license_by_id.remove(&token.token_id);
             // self.token_metadata_by_id.remove(&token.token_id);
             // Refund any deposit
             let _ = refund_deposit(0, Some(predecessor_id), None);
 
-            let msg = result.unwrap_err();
             env::log_str( &format!("Error: {}", msg));
-            return NFTMintResult{license_token:None, error:msg}
+            return NFTMintResult{token_id: Some(token.token_id), license_token:None, receipt_id: None, error:msg}
         }
 
         let license_sold = self.nft_token_supply_for_asset(asset_id.clone());
         inventory_contract::ext(self.inventory_id.clone()).with_static_gas(Gas::ONE_TERA * 3).on_nft_mint(
             asset_id, license_sold
         );
-        // Process fees only if it is not a gift
-        if !opts.mint_opt.is_gift {
-            self.process_fees(asset_license_price.clone(), inventory_owner);
+        // Pay out the fees only if it is not a gift
+        if let Some(receipt_id) = receipt_id {
+            self.internal_complete_sales(&[receipt_id], result.unwrap());
         }
 
         // Log the serialized json.
         self.log_event(&mint_result.unwrap().to_string());
 
         NFTMintResult{
+            token_id: Some(token.token_id),
             license_token: Some(lic_token),
+            receipt_id,
             error: String::new(),
         }
     }
 
-    #[private]
-    pub fn process_fees(&self, base_deposit: Balance, master_account: AccountId) {
-        // base_deposit -> 97.5% base_account, 2.5% benefit
+    // Find the SKU to mint and its inventory license.
+    pub(crate) fn internal_mint_sku(
+        &self,
+        asset: &JsonAssetToken,
+        inv_metadata: &ExtendedInventoryMetadata,
+        sku_id: &Option<String>,
+        from_method: &str,
+    ) -> Result<(AssetLicense, Option<InventoryLicense>), String> {
+        let asset_license_opt = asset.licenses.as_ref().unwrap().iter().find(
+            |x| &x.sku_id == sku_id
+        );
+
+        if asset_license_opt.is_none() {
+            return Err(format!("Asset license not found by sku_id {}", sku_id.clone().unwrap_or_default()))
+        }
+        let asset_license = asset_license_opt.unwrap().clone();
+
+        if asset_license.hidden.unwrap_or(false) && from_method != MINT_OWNER_METHOD {
+            return Err("Asset license could not be minted using this method.".to_string())
+        }
+
+        let inv_license = inv_metadata.metadata.licenses.iter().find(
+            |x| asset_license.license_id.is_some() && Some(&x.license_id) == asset_license.license_id.as_ref()
+        ).cloned();
+        Ok((asset_license, inv_license))
+    }
+
+    pub(crate) fn internal_issue_mint_license(
+        &self,
+        asset: &JsonAssetToken,
+        inv_metadata: &ExtendedInventoryMetadata,
+        asset_license: AssetLicense,
+        inv_license: Option<InventoryLicense>,
+        token_id: TokenId,
+        receiver_id: AccountId,
+    ) -> Result<LicenseToken, String> {
+        let royalty = self.get_mint_royalty(&asset_license, &inv_metadata.owner_id)?;
+        let mut lic_token = asset.issue_new_license(inv_license, asset_license, token_id);
+        lic_token.owner_id = receiver_id;
+        lic_token.royalty = royalty;
+        seed_transfer_fee_receiver(&mut lic_token, &inv_metadata.owner_id);
+        Ok(lic_token)
+    }
+
+    // Explicit token IDs must be free, otherwise the next numeric ID is reserved right away,
+    // so concurrent mints never get the same one. An explicit numeric ID moves the counter
+    // past it, so it is not generated for another mint while this one is in flight.
+    pub(crate) fn internal_mint_token_id(&mut self, token_id: Option<TokenId>) -> TokenId {
+        match token_id {
+            Some(token_id) => {
+                if self.tokens_by_id.get(&token_id).is_some() {
+                    env::panic_str("Token already exists")
+                }
+                self.internal_bump_token_id(&token_id);
+                token_id
+            }
+            None => self.internal_next_token_id(),
+        }
+    }
+
+    // Perpetual royalties of a new token: the licensor share comes from the SKU params,
+    // the benefit share from the benefit config.
+    fn get_mint_royalty(&self, asset_license: &AssetLicense, licensor_id: &AccountId) -> Result<HashMap<AccountId, u32>, String> {
+        let mut royalty: HashMap<AccountId, u32> = HashMap::new();
+        if let Some(licensor_royalty) = asset_license.get_params().royalty {
+            if licensor_royalty > 0 {
+                royalty.insert(licensor_id.clone(), licensor_royalty);
+            }
+        }
+        if let Some(benefit_config) = self.benefit_config.as_ref() {
+            if let Some(benefit_royalty) = benefit_config.royalty {
+                if benefit_royalty > 0 {
+                    *royalty.entry(benefit_config.account_id.clone()).or_insert(0) += benefit_royalty;
+                }
+            }
+        }
+        if royalty.values().sum::<u32>() > 10_000 {
+            return Err("Total royalty cannot be greater than 100%".to_string())
+        }
+        Ok(royalty)
+    }
+
+    // base_deposit -> 97.5% base_account, 2.5% benefit
+    pub(crate) fn split_fees(&self, base_deposit: Balance) -> (Balance, Balance) {
         let benefit_fee_milli_percent = if self.benefit_config.is_some() {
             unsafe {self.benefit_config.clone().unwrap_unchecked().fee_milli_percent_amount}
         } else {
             0
         };
-        let benefit_fee = base_deposit / (1e5 as u128) * (benefit_fee_milli_percent as u128);
-        let base_amount = base_deposit - benefit_fee;
+        let benefit_fee = mul_div_round(base_deposit, benefit_fee_milli_percent as u128, 100_000).unwrap_or(0);
+        (base_deposit - benefit_fee, benefit_fee)
+    }
 
-        Promise::new(master_account).transfer(base_amount);
-        if benefit_fee != 0 {
-            unsafe {
-                Promise::new(self.benefit_config.clone().unwrap_unchecked().account_id).transfer(benefit_fee);
-            }
-        }
+    pub(crate) fn internal_rollback_mint(&mut self, token: &Token) {
+        self.internal_remove_token_from_owner(&token.owner_id, &token.token_id);
+        self.internal_remove_token_from_asset(&token.asset_id, &token.token_id);
+        self.internal_unindex_token(token);
+        self.tokens_by_id.remove(&token.token_id);
+        self.internal_rollback_token_history(&token.token_id, TokenHistoryKind::Mint);
     }
 
     #[private]
@@ -409,14 +468,18 @@ impl Contract {
             owner_id: lic_token.owner_id.clone(),
             license: lic_token.license,
             metadata: lic_token.metadata,
-            // royalty: lic_token.royalty.clone(),
+            //keep approvals of restored tokens, the next approval ID goes after the latest one
+            next_approval_id: lic_token.approved_account_ids.values().max().map(|x| x + 1).unwrap_or(0),
+            approved_account_ids: lic_token.approved_account_ids,
+            royalty: lic_token.royalty,
         };
-        let exists = self.tokens_by_id.insert(&lic_token.token_id, &token);
-        if exists.is_some() {
+        // checked before inserting, so that the existing token is left intact
+        if self.tokens_by_id.get(&lic_token.token_id).is_some() {
             let msg = "Token already exists";
             env::log_str( &format!("Error: {}",msg));
             return Err(msg.to_string())
         }
+        self.tokens_by_id.insert(&lic_token.token_id, &token);
         // self.token_metadata_by_id.insert(&lic_token.token_id, &lic_token.metadata);
         //insert the token ID and license
         // if lic_token.license.is_some() {
@@ -427,6 +490,9 @@ impl Contract {
 
         self.internal_add_token_to_owner(&lic_token.owner_id, &lic_token.token_id);
         self.internal_add_token_to_asset(&lic_token.asset_id, &lic_token.token_id);
+        self.internal_index_token(&token);
+        self.internal_bump_token_id(&token.token_id);
+        self.internal_add_token_history(&token.token_id, TokenHistoryKind::Mint, &token.owner_id, token.license.clone());
 
         // Construct the mint log as per the events standard.
         let nft_mint_log: EventLog = EventLog {
@@ -0,0 +1,351 @@
+use std::collections::{HashMap, HashSet};
+use near_sdk::{Gas, PromiseResult, serde_json};
+use near_sdk::serde::de::DeserializeOwned;
+use common_types::prices::Asset;
+use common_types::utils::{balance_from_string, format_balance, mul_div_round};
+use crate::*;
+
+const MINT_BATCH_METHOD: &str = "nft_mint_batch";
+// every item costs a policy check, keep the whole cart within the transaction gas
+pub const MAX_MINT_BATCH_ITEMS: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintBatchItem {
+    pub asset_id: String,
+    pub sku_id: Option<String>,
+    pub receiver_id: AccountId,
+    pub token_id: Option<TokenId>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NFTMintBatchItem {
+    pub token_id: TokenId,
+    // charged SKU price
+    pub price: U128,
+    // storage paid for the minted token
+    pub storage_cost: U128,
+    pub license_token: Option<LicenseToken>,
+    pub receipt_id: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NFTMintBatchResult {
+    pub items: Vec<NFTMintBatchItem>,
+    // part of the attached deposit sent back to the buyer
+    pub refund: U128,
+    pub error: String,
+}
+
+// deserialize the result of the joint promise at index, None if the call failed
+fn promise_result_value<T: DeserializeOwned>(index: u64) -> Option<T> {
+    match env::promise_result(index) {
+        PromiseResult::Successful(value) => serde_json::from_slice(&value).ok(),
+        _ => None,
+    }
+}
+
+// the whole deposit is refunded on failure, nothing is minted
+fn failed_mint_batch(token_ids: Vec<TokenId>, error: String) -> NFTMintBatchResult {
+    env::log_str(&format!("Error: {}", error));
+    NFTMintBatchResult {
+        items: token_ids.into_iter().map(|token_id| NFTMintBatchItem {
+            token_id,
+            price: U128(0),
+            storage_cost: U128(0),
+            license_token: None,
+            receipt_id: None,
+        }).collect(),
+        refund: U128(env::attached_deposit()),
+        error,
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // Mints a cart of SKUs all-or-nothing, charging a single deposit.
+    #[payable]
+    pub fn nft_mint_batch(&mut self, items: Vec<MintBatchItem>) -> Promise {
+        if items.is_empty() {
+            env::panic_str("No items to mint")
+        }
+        if items.len() > MAX_MINT_BATCH_ITEMS {
+            env::panic_str(&format!("Cannot mint more than {} items at once", MAX_MINT_BATCH_ITEMS))
+        }
+
+        let mut items = items;
+        let mut token_ids: HashSet<TokenId> = HashSet::new();
+        let mut asset_ids: Vec<String> = Vec::new();
+        for item in items.iter_mut() {
+            let token_id = self.internal_mint_token_id(item.token_id.take());
+            if !token_ids.insert(token_id.clone()) {
+                env::panic_str(&format!("Duplicate token ID {}", token_id))
+            }
+            item.token_id = Some(token_id);
+            if !asset_ids.contains(&item.asset_id) {
+                asset_ids.push(item.asset_id.clone());
+            }
+        }
+
+        // Schedule calls to metadata, price and every asset of the cart once
+        let mut promise_inventory: Promise = inventory_contract::ext(self.inventory_id.clone())
+            .with_unused_gas_weight(4).inventory_metadata()
+            .and(self.internal_get_near_price(4));
+        for asset_id in asset_ids.iter() {
+            promise_inventory = promise_inventory.and(
+                inventory_contract::ext(self.inventory_id.clone())
+                    .with_unused_gas_weight(4).asset_token(asset_id.clone())
+            );
+        }
+
+        let predecessor_id = env::predecessor_account_id();
+        promise_inventory.then(
+            Self::ext(env::current_account_id())
+                .with_attached_deposit(env::attached_deposit())
+                .with_unused_gas_weight(280)
+                .on_nft_mint_batch(items, asset_ids, predecessor_id)
+        )
+    }
+
+    #[private]
+    #[payable]
+    pub fn on_nft_mint_batch(
+        &mut self,
+        items: Vec<MintBatchItem>,
+        asset_ids: Vec<String>,
+        predecessor_id: AccountId,
+    ) -> PromiseOrValue<NFTMintBatchResult> {
+        match self.ensure_nft_mint_batch(&items, &asset_ids, predecessor_id.clone()) {
+            Ok(result) => result,
+            Err(msg) => {
+                let _ = refund_deposit(0, Some(predecessor_id), None);
+                PromiseOrValue::Value(failed_mint_batch(
+                    items.into_iter().map(|x| x.token_id.unwrap()).collect(), msg,
+                ))
+            }
+        }
+    }
+
+    fn ensure_nft_mint_batch(
+        &mut self,
+        items: &[MintBatchItem],
+        asset_ids: &[String],
+        predecessor_id: AccountId,
+    ) -> Result<PromiseOrValue<NFTMintBatchResult>, String> {
+        // 1. Check callback results first: metadata, price, then assets in order.
+        let inv_metadata: ExtendedInventoryMetadata = promise_result_value(0)
+            .ok_or("Failed call inventory_metadata".to_string())?;
+        let near_price_res: Option<Asset> = promise_result_value::<Option<Asset>>(1).flatten();
+        let mut assets: HashMap<String, JsonAssetToken> = HashMap::new();
+        for (index, asset_id) in asset_ids.iter().enumerate() {
+            let asset = promise_result_value::<Option<JsonAssetToken>>(2 + index as u64).flatten()
+                .ok_or(format!("Failed call asset_token for {}", asset_id))?;
+            assets.insert(asset_id.clone(), asset);
+        }
+
+        let near_price = &self.internal_near_price(Ok(near_price_res))?;
+
+        // 2. Price every item and issue its license.
+        let mut lic_tokens: Vec<LicenseToken> = Vec::new();
+        let mut prices: Vec<Balance> = Vec::new();
+        let mut terms: Vec<SaleTerms> = Vec::new();
+        let mut minimum_total: Balance = 0;
+        for item in items {
+            let asset = &assets[&item.asset_id];
+            let (mut asset_license, inv_license) = self.internal_mint_sku(
+                asset, &inv_metadata, &item.sku_id, MINT_BATCH_METHOD,
+            )?;
+            terms.push(SaleTerms::new(&asset_license, Some(near_price), asset.revenue_payees(
+                inv_metadata.metadata.revenue_split.as_ref(), inv_metadata.owner_id.clone(),
+            )));
+            asset_license.price = asset_license.get_near_cost(near_price);
+            let price = balance_from_string(asset_license.price.clone());
+            minimum_total += minimum_price(price, self.sku_slippage_percents(&asset_license));
+            prices.push(price);
+            lic_tokens.push(self.internal_issue_mint_license(
+                asset, &inv_metadata, asset_license, inv_license,
+                item.token_id.clone().unwrap(), item.receiver_id.clone(),
+            )?);
+        }
+
+        // 3. The deposit must cover the whole cart, each item within its slippage.
+        let deposit = env::attached_deposit();
+        let storage_reserve = self.settings.storage_reserve.0 * items.len() as u128;
+        let total: Balance = prices.iter().sum();
+        let reserved_price = self.internal_charge(total, minimum_total, storage_reserve).ok_or(format!(
+            "Attached deposit of {} NEAR is less than cart price of {} NEAR (with slippage, at least {} NEAR)",
+            format_balance(deposit),
+            format_balance(total),
+            format_balance(minimum_total),
+        ))?;
+        if reserved_price < total {
+            // charge each item its share of the reserved amount, the last one takes the rounding
+            let last = prices.len() - 1;
+            for price in prices[..last].iter_mut() {
+                *price = mul_div_round(*price, reserved_price, total).unwrap_or(0);
+            }
+            prices[last] = reserved_price.saturating_sub(prices[..last].iter().sum::<Balance>());
+        }
+
+        if let Some(skip_policies) = self.metadata.get().unwrap().skip_policies {
+            if skip_policies {
+                return Ok(PromiseOrValue::Value(self.internal_mint_batch(
+                    lic_tokens, prices, terms, predecessor_id,
+                )))
+            }
+        }
+
+        // 4. Check every item against the policies, counting the items before it as issued.
+        let mut promise_checks: Option<Promise> = None;
+        for (index, lic_token) in lic_tokens.iter().enumerate() {
+            let asset = &assets[&lic_token.asset_id];
+            let mut full_inventory = self.get_full_inventory(
+                self.inventory_id.clone(), asset.clone(), inv_metadata.metadata.clone(),
+            );
+            full_inventory.issued_licenses.extend(
+                lic_tokens[..index].iter().filter(|x| x.asset_id == lic_token.asset_id).map(|x| x.shrink())
+            );
+            let promise_new: Promise = policy_rules_contract::ext(self.policy_contract.clone())
+                .with_unused_gas_weight(100).check_new(
+                full_inventory,
+                lic_token.shrink(),
+                asset.policy_rules.clone(),
+                asset.upgrade_rules.clone(),
+            );
+            promise_checks = Some(match promise_checks {
+                Some(promise) => promise.and(promise_new),
+                None => promise_new,
+            });
+        }
+
+        let on_check_promise = promise_checks.unwrap().then(
+            Self::ext(env::current_account_id())
+                .with_attached_deposit(deposit)
+                .with_unused_gas_weight(10 * lic_tokens.len() as u64)
+                .on_check_new_batch(lic_tokens, prices, terms, predecessor_id)
+        );
+        Ok(PromiseOrValue::Promise(on_check_promise))
+    }
+
+    #[private]
+    #[payable]
+    pub fn on_check_new_batch(
+        &mut self,
+        lic_tokens: Vec<LicenseToken>,
+        prices: Vec<Balance>,
+        terms: Vec<SaleTerms>,
+        predecessor_id: AccountId,
+    ) -> NFTMintBatchResult {
+        for (index, lic_token) in lic_tokens.iter().enumerate() {
+            let error = match promise_result_value::<IsAvailableResponseData>(index as u64) {
+                None => Some("Failed call check_new()".to_string()),
+                Some(res) if !res.result => Some(format!("Token {}: {}", lic_token.token_id, res.reason_not_available)),
+                Some(_) => None,
+            };
+            if let Some(msg) = error {
+                let _ = refund_deposit(0, Some(predecessor_id), None);
+                return failed_mint_batch(lic_tokens.into_iter().map(|x| x.token_id).collect(), msg)
+            }
+        }
+        self.internal_mint_batch(lic_tokens, prices, terms, predecessor_id)
+    }
+}
+
+impl Contract {
+    // Mints all the tokens or none of them.
+    fn internal_mint_batch(
+        &mut self,
+        lic_tokens: Vec<LicenseToken>,
+        prices: Vec<Balance>,
+        terms: Vec<SaleTerms>,
+        predecessor_id: AccountId,
+    ) -> NFTMintBatchResult {
+        let initial_storage_usage = env::storage_usage();
+        let token_ids: Vec<TokenId> = lic_tokens.iter().map(|x| x.token_id.clone()).collect();
+        let mut minted: Vec<Token> = Vec::new();
+        let mut items: Vec<NFTMintBatchItem> = Vec::new();
+        let mut logs: Vec<EventLog> = Vec::new();
+        let mut error: Option<String> = None;
+
+        for (lic_token, price) in lic_tokens.into_iter().zip(prices.iter()) {
+            let storage_usage = env::storage_usage();
+            match self.internal_mint(lic_token.clone()) {
+                Ok(log) => logs.push(log),
+                Err(msg) => {
+                    error = Some(msg);
+                    break
+                }
+            }
+            minted.push(self.tokens_by_id.get(&lic_token.token_id).unwrap());
+            items.push(NFTMintBatchItem {
+                token_id: lic_token.token_id.clone(),
+                price: U128(*price),
+                storage_cost: U128(Balance::from(env::storage_usage() - storage_usage) * env::storage_byte_cost()),
+                license_token: Some(lic_token),
+                receipt_id: None,
+            });
+        }
+
+        // Record the sales before measuring the storage, so the buyer pays for their receipts too.
+        let mut receipt_ids: Vec<u64> = Vec::new();
+        if error.is_none() {
+            for (item, terms) in items.iter_mut().zip(terms) {
+                let receipt_id = self.internal_record_sale(
+                    ReceiptKind::Mint, item.license_token.as_ref().unwrap(), &predecessor_id, terms, SalePayment {
+                        ft_token_id: None,
+                        amount: item.price.0,
+                        refund: 0,
+                    },
+                );
+                item.receipt_id = Some(receipt_id);
+                receipt_ids.push(receipt_id);
+            }
+        }
+
+        let total: Balance = prices.iter().sum();
+        let mut refund: Balance = 0;
+        if error.is_none() {
+            // refund any excess storage if the user attached too much.
+            match refund_storage(initial_storage_usage, Some(predecessor_id.clone()), Some(total)) {
+                Ok(amount) => refund = amount,
+                Err(msg) => error = Some(msg),
+            }
+        }
+        if let Some(msg) = error {
+            // Rollback all changes!
+            self.internal_rollback_sales(&receipt_ids);
+            for token in minted.iter() {
+                self.internal_rollback_mint(token);
+            }
+            let _ = refund_deposit(0, Some(predecessor_id), None);
+            return failed_mint_batch(token_ids, msg)
+        }
+
+        let mut asset_ids: Vec<String> = Vec::new();
+        for token in minted.iter() {
+            if !asset_ids.contains(&token.asset_id) {
+                asset_ids.push(token.asset_id.clone());
+            }
+        }
+        for asset_id in asset_ids {
+            let license_sold = self.nft_token_supply_for_asset(asset_id.clone());
+            inventory_contract::ext(self.inventory_id.clone()).with_static_gas(Gas::ONE_TERA * 3).on_nft_mint(
+                asset_id, license_sold
+            );
+        }
+        // the refund of the whole cart is recorded on the receipt of its last item
+        self.internal_complete_sales(&receipt_ids, refund);
+
+        for log in logs {
+            self.log_event(&log.to_string());
+        }
+
+        NFTMintBatchResult {
+            items,
+            refund: U128(refund),
+            error: String::new(),
+        }
+    }
+}
@@ -1,4 +1,6 @@
 use crate::*;
+use crate::internal::*;
+use crate::storage::storage_cost_since;
 use near_sdk::{ext_contract, Gas, PromiseResult};
 
 // const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(10_000_000_000_000);
@@ -6,6 +8,15 @@ use near_sdk::{ext_contract, Gas, PromiseResult};
 // const NO_DEPOSIT: Balance = 0;
 const MIN_GAS_FOR_NFT_TRANSFER_CALL: Gas = Gas(50_000_000_000_000);
 
+// License transfer fee paid with nft_transfer_call, held until the receiver keeps the token.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TransferFee {
+    pub receiver_id: AccountId,
+    pub payer_id: AccountId,
+    pub amount: U128,
+}
+
 pub trait NonFungibleTokenCore {
     //transfers an NFT to a receiver ID
     fn nft_transfer(
@@ -47,7 +58,7 @@ trait NonFungibleTokenReceiver {
 }
 
 #[ext_contract(ext_self)]
-trait NonFungibleTokenResolver {
+pub trait NonFungibleTokenResolver {
     /*
         resolves the promise of the cross contract call to the receiver contract
         this is stored on THIS contract and is meant to analyze what happened in the cross contract call when nft_on_transfer was called
@@ -64,6 +75,10 @@ trait NonFungibleTokenResolver {
         approved_account_ids: Option<HashMap<AccountId, u64>>,
         //we introduce a memo for logging the transfer event
         memo: Option<String>,
+        //the license transfer fee to pay, or to refund if the transfer is reverted
+        transfer_fee: Option<TransferFee>,
+        //storage paid by the owner for the history entry of a reverted transfer
+        storage_reserve: U128,
     ) -> bool;
 }
 
@@ -81,25 +96,31 @@ impl NonFungibleTokenCore for Contract {
         approval_id: Option<u64>,
         memo: Option<String>,
     ) {
-        //assert that the user attached exactly 1 yoctoNEAR. This is for security and so that the user will be redirected to the NEAR wallet. 
-        assert_one_yocto();
+        //assert that the user attached exactly 1 yoctoNEAR or the license transfer fee. This is for security and so that the user will be redirected to the NEAR wallet. 
+        let transfer_fee = self.internal_take_transfer_fee(&token_id);
         //get the sender to transfer the token from the sender to the receiver
         let sender_id = env::predecessor_account_id();
 
         //call the internal transfer method and get back the previous token so we can refund the approved account IDs
-        self.internal_transfer(
+        let initial_storage_usage = env::storage_usage();
+        let previous_token = self.internal_transfer(
             &sender_id,
             &receiver_id,
             &token_id,
             approval_id,
             memo,
         );
+        //the owner pays for the storage of the transfer and its history entry
+        if let Err(msg) = self.internal_charge_storage(&previous_token.owner_id, initial_storage_usage) {
+            env::panic_str(&msg)
+        }
+        settle_transfer_fee(transfer_fee, true);
 
         //we refund the owner for releasing the storage used up by the approved account IDs
-        // refund_approved_account_ids(
-        //     previous_token.owner_id.clone(),
-        //     &previous_token.approved_account_ids,
-        // );
+        refund_approved_account_ids(
+            previous_token.owner_id.clone(),
+            &previous_token.approved_account_ids,
+        );
     }
 
     // Implementation of the transfer call method.
@@ -114,8 +135,9 @@ impl NonFungibleTokenCore for Contract {
         memo: Option<String>,
         msg: String,
     ) -> PromiseOrValue<bool> {
-        //assert that the user attached exactly 1 yocto for security reasons. 
-        assert_one_yocto();
+        //assert that the user attached exactly 1 yocto or the license transfer fee for security reasons.
+        //the fee is paid once the receiver keeps the token
+        let transfer_fee = self.internal_take_transfer_fee(&token_id);
 
         //get the GAS attached to the call
         let attached_gas = env::prepaid_gas();
@@ -136,6 +158,7 @@ impl NonFungibleTokenCore for Contract {
         let sender_id = env::predecessor_account_id();
 
         //transfer the token and get the previous token object
+        let initial_storage_usage = env::storage_usage();
         let previous_token = self.internal_transfer(
             &sender_id,
             &receiver_id,
@@ -143,6 +166,12 @@ impl NonFungibleTokenCore for Contract {
             approval_id,
             memo.clone(),
         );
+        //the owner pays for the storage of the transfer, and upfront for the history entry of its revert,
+        //which is credited back if the receiver keeps the token
+        let storage_reserve = storage_cost_since(initial_storage_usage);
+        if let Err(msg) = self.internal_charge_storage_cost(&previous_token.owner_id, 2 * storage_reserve) {
+            env::panic_str(&msg)
+        }
 
         //default the authorized_id to none
         let mut authorized_id = None; 
@@ -168,8 +197,10 @@ impl NonFungibleTokenCore for Contract {
             previous_token.owner_id,
             receiver_id,
             token_id,
-            None,
+            Some(previous_token.approved_account_ids),
             memo, // we introduce a memo for logging in the events standard
+            transfer_fee,
+            U128(storage_reserve),
         )).into()
     }
 
@@ -187,8 +218,13 @@ impl NonFungibleTokenCore for Contract {
                 owner_id: token.owner_id,
                 asset_id: token.asset_id,
                 metadata: token.metadata,
-                license: token.license,
-                // approved_account_ids: token.approved_account_ids,
+                //the stored status doesn't reflect expiration, return the actual one
+                license: token.license.map(|mut license| {
+                    license.status = Some(license.status());
+                    license
+                }),
+                approved_account_ids: token.approved_account_ids,
+                royalty: token.royalty,
             })
         } else { //if there wasn't a token ID in the tokens_by_id collection, we return None
             None
@@ -215,7 +251,13 @@ impl NonFungibleTokenResolver for Contract {
         approved_account_ids: Option<HashMap<AccountId, u64>>,
         //we introduce a memo for logging the transfer event
         memo: Option<String>,
+        //the license transfer fee to pay, or to refund if the transfer is reverted
+        transfer_fee: Option<TransferFee>,
+        //storage paid by the owner for the history entry of a reverted transfer
+        storage_reserve: U128,
     ) -> bool {
+        let approved_account_ids = approved_account_ids.unwrap_or_default();
+
         // Whether receiver wants to return token back to the sender, based on `nft_on_transfer`
         // call result.
         if let PromiseResult::Successful(value) = env::promise_result(0) {
@@ -228,7 +270,9 @@ impl NonFungibleTokenResolver for Contract {
                         revert the original transfer and thus we can just return true since nothing went wrong.
                     */
                     //we refund the owner for releasing the storage used up by the approved account IDs
-                    // refund_approved_account_ids(owner_id, &approved_account_ids);
+                    self.internal_credit_storage(&owner_id, storage_reserve.0);
+                    refund_approved_account_ids(owner_id, &approved_account_ids);
+                    settle_transfer_fee(transfer_fee, true);
                     return true;
                 }
             }
@@ -238,7 +282,9 @@ impl NonFungibleTokenResolver for Contract {
         let mut token = if let Some(token) = self.tokens_by_id.get(&token_id) {
             if token.owner_id != receiver_id {
                 //we refund the owner for releasing the storage used up by the approved account IDs
-                // refund_approved_account_ids(owner_id, &approved_account_ids);
+                self.internal_credit_storage(&owner_id, storage_reserve.0);
+                refund_approved_account_ids(owner_id, &approved_account_ids);
+                settle_transfer_fee(transfer_fee, true);
                 // The token is not owned by the receiver anymore. Can't return it.
                 return true;
             }
@@ -246,25 +292,36 @@ impl NonFungibleTokenResolver for Contract {
         //if there isn't a token object, it was burned and so we return true
         } else {
             //we refund the owner for releasing the storage used up by the approved account IDs
-            // refund_approved_account_ids(owner_id, &approved_account_ids);
+            self.internal_credit_storage(&owner_id, storage_reserve.0);
+            refund_approved_account_ids(owner_id, &approved_account_ids);
+            settle_transfer_fee(transfer_fee, true);
             return true;
         };
 
+        let initial_storage_usage = env::storage_usage();
+
         //we remove the token from the receiver
         self.internal_remove_token_from_owner(&receiver_id.clone(), &token_id);
         //we add the token to the original owner
         self.internal_add_token_to_owner(&owner_id, &token_id);
 
         //we change the token struct's owner to be the original owner 
+        self.internal_unindex_token(&token);
         token.owner_id = owner_id.clone();
+        self.internal_index_token(&token);
 
         //we refund the receiver any approved account IDs that they may have set on the token
-        // refund_approved_account_ids(receiver_id.clone(), &token.approved_account_ids);
+        refund_approved_account_ids(receiver_id.clone(), &token.approved_account_ids);
         //reset the approved account IDs to what they were before the transfer
-        // token.approved_account_ids = approved_account_ids;
+        token.approved_account_ids = approved_account_ids;
 
         //we inset the token back into the tokens_by_id collection
         self.tokens_by_id.insert(&token_id, &token);
+        self.internal_add_token_history(&token_id, TokenHistoryKind::TransferReverted, &owner_id, None);
+        //the reserve covers the revert, the rest of it goes back to the owner
+        let storage_cost = storage_cost_since(initial_storage_usage);
+        self.internal_credit_storage(&owner_id, storage_reserve.0.saturating_sub(storage_cost));
+        settle_transfer_fee(transfer_fee, false);
 
         /*
             We need to log that the NFT was reverted back to the original owner.
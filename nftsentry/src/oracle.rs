@@ -0,0 +1,54 @@
+use near_sdk::PromiseError;
+use common_types::prices::{Asset, FallbackPrice, OracleConfig, Price, get_near_price, select_near_price};
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_oracle_config(&mut self, config: OracleConfig) {
+        if env::predecessor_account_id() != self.owner_id {
+            env::panic_str("Unauthorized")
+        }
+        if let Err(msg) = config.validate() {
+            env::panic_str(&msg)
+        }
+        self.oracle_config = config;
+    }
+
+    pub fn oracle_config(&self) -> OracleConfig {
+        self.oracle_config.clone()
+    }
+
+    // The fallback price is used until it expires while the oracle has no fresh reports.
+    pub fn set_fallback_price(&mut self, fallback_price: Option<FallbackPrice>) {
+        if env::predecessor_account_id() != self.owner_id {
+            env::panic_str("Unauthorized")
+        }
+        if let Some(Err(msg)) = fallback_price.as_ref().map(|x| x.validate()) {
+            env::panic_str(&msg)
+        }
+        self.fallback_price = fallback_price;
+    }
+
+    pub fn fallback_price(&self) -> Option<FallbackPrice> {
+        self.fallback_price.clone()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_get_near_price(&self, gas_weight: u64) -> Promise {
+        get_near_price(self.oracle_config.oracle_id(), gas_weight)
+    }
+
+    // NEAR price from the result of internal_get_near_price
+    pub(crate) fn internal_near_price(&self, price_res: Result<Option<Asset>, PromiseError>) -> Result<Price, String> {
+        let asset = price_res.ok().flatten();
+        let price = select_near_price(
+            asset.as_ref(),
+            env::block_timestamp_ms(),
+            self.oracle_config.max_age_sec(),
+            self.fallback_price.as_ref(),
+        )?;
+        env::log_str(&format!("NEAR price: {} USD", price.string_price()));
+        Ok(price)
+    }
+}
@@ -0,0 +1,89 @@
+use near_sdk::{Gas, PromiseResult};
+use common_types::utils::assert_one_yocto;
+use crate::*;
+use crate::ft_payment::GAS_FOR_FT_TRANSFER;
+
+const GAS_FOR_ON_PAYOUT: Gas = Gas(5_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    // Claimable sale proceeds of the account, in NEAR or in the given fungible token.
+    pub fn balance_of_proceeds(&self, account_id: AccountId, ft_token_id: Option<AccountId>) -> U128 {
+        U128(self.proceeds.get(&(account_id, ft_token_id)).unwrap_or(0))
+    }
+
+    // Sends the claimable proceeds to the caller, all of them if the amount is not set.
+    #[payable]
+    pub fn withdraw(&mut self, ft_token_id: Option<AccountId>, amount: Option<U128>) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self.balance_of_proceeds(account_id.clone(), ft_token_id.clone()).0;
+        let amount = amount.map(|x| x.0).unwrap_or(balance);
+        if amount == 0 {
+            env::panic_str("Nothing to withdraw")
+        }
+        if amount > balance {
+            env::panic_str(&format!("Not enough proceeds, {} available", balance))
+        }
+        self.internal_payout(account_id, ft_token_id, amount)
+    }
+
+    // Credits the proceeds back if the payout transfer failed.
+    #[private]
+    pub fn on_payout(&mut self, account_id: AccountId, ft_token_id: Option<AccountId>, amount: U128) -> bool {
+        if !matches!(env::promise_result(0), PromiseResult::Successful(_)) {
+            env::log_str(&format!("Error: failed payout of {} to {}, credited back", amount.0, account_id));
+            self.internal_credit_proceeds(&account_id, &ft_token_id, amount.0);
+            return false
+        }
+
+        let nft_withdraw_proceeds_log: EventLog = EventLog {
+            standard: NFT_LICENSE_STANDARD_NAME.to_string(),
+            version: NFT_LICENSE_SPEC.to_string(),
+            event: EventLogVariant::NftWithdrawProceeds(vec![NftWithdrawProceedsLog {
+                account_id: account_id.to_string(),
+                ft_token_id: ft_token_id.map(|x| x.to_string()),
+                amount,
+                memo: None,
+            }]),
+        };
+        self.log_event(&nft_withdraw_proceeds_log.to_string());
+        true
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_credit_proceeds(&mut self, account_id: &AccountId, ft_token_id: &Option<AccountId>, amount: Balance) {
+        let key = (account_id.clone(), ft_token_id.clone());
+        let balance = self.proceeds.get(&key).unwrap_or(0);
+        self.proceeds.insert(&key, &(balance + amount));
+    }
+
+    pub(crate) fn internal_debit_proceeds(&mut self, account_id: &AccountId, ft_token_id: &Option<AccountId>, amount: Balance) {
+        let key = (account_id.clone(), ft_token_id.clone());
+        let balance = self.proceeds.get(&key).unwrap_or(0);
+        if balance <= amount {
+            self.proceeds.remove(&key);
+        } else {
+            self.proceeds.insert(&key, &(balance - amount));
+        }
+    }
+
+    // Takes the amount off the ledger and transfers it, on_payout credits it back on failure.
+    pub(crate) fn internal_payout(&mut self, account_id: AccountId, ft_token_id: Option<AccountId>, amount: Balance) -> Promise {
+        self.internal_debit_proceeds(&account_id, &ft_token_id, amount);
+
+        let transfer = match ft_token_id.as_ref() {
+            Some(ft_token_id) => ft_contract::ext(ft_token_id.clone())
+                .with_attached_deposit(1)
+                .with_static_gas(GAS_FOR_FT_TRANSFER)
+                .ft_transfer(account_id.clone(), U128(amount), None),
+            None => Promise::new(account_id.clone()).transfer(amount),
+        };
+        transfer.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_ON_PAYOUT)
+                .on_payout(account_id, ft_token_id, U128(amount))
+        )
+    }
+}
@@ -0,0 +1,292 @@
+use near_sdk::PromiseError;
+use common_types::prices::{Asset, Price};
+use common_types::types::{AssetLicense, LicenseGeneral};
+use common_types::utils::balance_from_string;
+use crate::*;
+use crate::mint::MINT_METHOD;
+
+// Price of a mint or a license update as the contract charges it at the current NEAR price.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PriceQuote {
+    //SKU price and currency as listed by the asset
+    pub listed_price: String,
+    pub listed_currency: String,
+    //oracle NEAR/USD price the NEAR cost is calculated with
+    pub near_price: Option<String>,
+    //charged price in yoctoNEAR, the price difference for an update
+    pub price: U128,
+    //the lowest price accepted within the slippage
+    pub slippage_percents: u32,
+    pub minimum_price: U128,
+    //kept from the deposit to cover the storage, the rest is refunded
+    pub storage_reserve: U128,
+    //deposit to attach to be charged the full price
+    pub deposit: U128,
+    pub benefit_fee: U128,
+    pub payees: Vec<ReceiptShare>,
+    pub error: String,
+}
+
+impl PriceQuote {
+    fn from_error(error: String) -> Self {
+        Self {
+            listed_price: String::new(),
+            listed_currency: String::new(),
+            near_price: None,
+            price: U128(0),
+            slippage_percents: 0,
+            minimum_price: U128(0),
+            storage_reserve: U128(0),
+            deposit: U128(0),
+            benefit_fee: U128(0),
+            payees: Vec::new(),
+            error,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // Quotes the deposit of nft_mint. Needs the inventory and the oracle, so it is called as
+    // a transaction without deposit, nft_view_quote_mint takes their data as arguments instead.
+    pub fn nft_quote_mint(&self, asset_id: String, sku_id: Option<String>) -> Promise {
+        let promise_meta: Promise = inventory_contract::ext(self.inventory_id.clone())
+            .with_unused_gas_weight(4).inventory_metadata();
+        let promise_asset: Promise = inventory_contract::ext(self.inventory_id.clone())
+            .with_unused_gas_weight(4).asset_token(asset_id);
+        let promise_price = self.internal_get_near_price(4);
+        promise_meta.and(promise_asset).and(promise_price).then(
+            Self::ext(env::current_account_id())
+                .with_unused_gas_weight(10)
+                .on_quote_mint(sku_id)
+        )
+    }
+
+    #[private]
+    pub fn on_quote_mint(
+        &self,
+        #[callback_result] metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        #[callback_result] asset_res: Result<JsonAssetToken, PromiseError>,
+        #[callback_result] price_res: Result<Option<Asset>, PromiseError>,
+        sku_id: Option<String>,
+    ) -> PriceQuote {
+        self.ensure_quote_mint(metadata_res, asset_res, price_res, sku_id)
+            .unwrap_or_else(PriceQuote::from_error)
+    }
+
+    fn ensure_quote_mint(
+        &self,
+        metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        asset_res: Result<JsonAssetToken, PromiseError>,
+        price_res: Result<Option<Asset>, PromiseError>,
+        sku_id: Option<String>,
+    ) -> Result<PriceQuote, String> {
+        let inv_metadata = metadata_res.map_err(|_| "Failed call inventory_metadata".to_string())?;
+        let asset = asset_res.map_err(|_| "Failed call asset_token".to_string())?;
+        let near_price = self.internal_near_price(price_res)?;
+        let (asset_license, _inv_license) = self.internal_mint_sku(
+            &asset, &inv_metadata, &sku_id, MINT_METHOD,
+        )?;
+        Ok(self.internal_quote_mint(&asset, &inv_metadata, &asset_license, &near_price).1)
+    }
+
+    // Quotes the deposit of nft_update_license. Needs the inventory and the oracle, so it is
+    // called as a transaction without deposit, nft_view_quote_update takes their data as arguments instead.
+    pub fn nft_quote_update(&self, token_id: TokenId, new_sku_id: String) -> Promise {
+        let token = self.nft_token(token_id.clone()).expect("Token does not exist");
+        let (inventory_id, asset_id, _license_id, _sku) = token.inventory_asset_license_sku();
+        let inventory_account_id = AccountId::new_unchecked(inventory_id);
+
+        let promise_meta: Promise = inventory_contract::ext(inventory_account_id.clone())
+            .with_unused_gas_weight(3).inventory_metadata();
+        let promise_asset: Promise = inventory_contract::ext(inventory_account_id.clone())
+            .with_unused_gas_weight(3).asset_token(asset_id);
+        let promise_price = self.internal_get_near_price(3);
+        promise_meta.and(promise_asset).and(promise_price).then(
+            Self::ext(env::current_account_id())
+                .with_unused_gas_weight(10)
+                .on_quote_update(token_id, new_sku_id)
+        )
+    }
+
+    // View variant of nft_quote_mint for callers that fetched the asset, the inventory metadata
+    // and the oracle NEAR price themselves, quoted the same way.
+    pub fn nft_view_quote_mint(
+        &self,
+        asset: JsonAssetToken,
+        inv_metadata: ExtendedInventoryMetadata,
+        near_price: Option<Asset>,
+        sku_id: Option<String>,
+    ) -> PriceQuote {
+        self.ensure_quote_mint(Ok(inv_metadata), Ok(asset), Ok(near_price), sku_id)
+            .unwrap_or_else(PriceQuote::from_error)
+    }
+
+    // View variant of nft_quote_update, see nft_view_quote_mint.
+    pub fn nft_view_quote_update(
+        &self,
+        asset: JsonAssetToken,
+        inv_metadata: ExtendedInventoryMetadata,
+        near_price: Option<Asset>,
+        token_id: TokenId,
+        new_sku_id: String,
+    ) -> PriceQuote {
+        self.ensure_quote_update(Ok(inv_metadata), Ok(asset), Ok(near_price), token_id, new_sku_id)
+            .unwrap_or_else(PriceQuote::from_error)
+    }
+
+    #[private]
+    pub fn on_quote_update(
+        &self,
+        #[callback_result] metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        #[callback_result] asset_res: Result<JsonAssetToken, PromiseError>,
+        #[callback_result] price_res: Result<Option<Asset>, PromiseError>,
+        token_id: TokenId,
+        new_sku_id: String,
+    ) -> PriceQuote {
+        self.ensure_quote_update(metadata_res, asset_res, price_res, token_id, new_sku_id)
+            .unwrap_or_else(PriceQuote::from_error)
+    }
+
+    fn ensure_quote_update(
+        &self,
+        metadata_res: Result<ExtendedInventoryMetadata, PromiseError>,
+        asset_res: Result<JsonAssetToken, PromiseError>,
+        price_res: Result<Option<Asset>, PromiseError>,
+        token_id: TokenId,
+        new_sku_id: String,
+    ) -> Result<PriceQuote, String> {
+        let metadata = metadata_res.map_err(|_| "Failed call inventory_metadata".to_string())?;
+        let asset = asset_res.map_err(|_| "Failed call asset_token".to_string())?;
+        let near_price = self.internal_near_price(price_res)?;
+        let token = self.nft_token(token_id).ok_or("Token does not exist".to_string())?;
+        let licenses = asset.licenses.as_ref().ok_or("Asset license not found".to_string())?;
+        let new_asset_license = licenses.iter().find(|x| x.sku_id.as_ref() == Some(&new_sku_id))
+            .ok_or("Asset license not found".to_string())?;
+        let old_asset_license = licenses.iter().find(|x| x.sku_id.as_ref() == Some(&token.sku_id()))
+            .ok_or("Asset license not found".to_string())?;
+        Ok(self.internal_quote_update(
+            &asset, &metadata, old_asset_license, new_asset_license, &near_price,
+        ).1)
+    }
+}
+
+impl Contract {
+    // Sale terms and price of minting the SKU of the asset.
+    pub(crate) fn internal_quote_mint(
+        &self,
+        asset: &JsonAssetToken,
+        inv_metadata: &ExtendedInventoryMetadata,
+        asset_license: &AssetLicense,
+        near_price: &Price,
+    ) -> (SaleTerms, PriceQuote) {
+        let payees = asset.revenue_payees(
+            inv_metadata.metadata.revenue_split.as_ref(), inv_metadata.owner_id.clone(),
+        );
+        let terms = SaleTerms::new(asset_license, Some(near_price), payees);
+        let price = balance_from_string(asset_license.get_near_cost(near_price));
+        let quote = self.internal_quote(price, asset_license, &terms);
+        (terms, quote)
+    }
+
+    // Sale terms and price difference of updating the license to the new SKU of the asset.
+    pub(crate) fn internal_quote_update(
+        &self,
+        asset: &JsonAssetToken,
+        inv_metadata: &ExtendedInventoryMetadata,
+        old_asset_license: &AssetLicense,
+        new_asset_license: &AssetLicense,
+        near_price: &Price,
+    ) -> (SaleTerms, PriceQuote) {
+        let payees = asset.revenue_payees(
+            inv_metadata.metadata.revenue_split.as_ref(), inv_metadata.owner_id.clone(),
+        );
+        let terms = SaleTerms::new(new_asset_license, Some(near_price), payees);
+        let price_diff = balance_from_string(
+            new_asset_license.get_near_cost(near_price)
+        ).saturating_sub(balance_from_string(
+            old_asset_license.get_near_cost(near_price)
+        ));
+        let quote = self.internal_quote(price_diff, new_asset_license, &terms);
+        (terms, quote)
+    }
+
+    fn internal_quote(&self, price: Balance, asset_license: &AssetLicense, terms: &SaleTerms) -> PriceQuote {
+        let slippage_percents = self.sku_slippage_percents(asset_license);
+        let storage_reserve = self.settings.storage_reserve.0;
+        let (benefit_fee, shares) = self.internal_split_sale(price, &terms.payees);
+        PriceQuote {
+            listed_price: terms.listed_price.clone(),
+            listed_currency: terms.listed_currency.clone(),
+            near_price: terms.near_price.clone(),
+            price: U128(price),
+            slippage_percents,
+            minimum_price: U128(minimum_price(price, slippage_percents)),
+            storage_reserve: U128(storage_reserve),
+            deposit: U128(price + storage_reserve),
+            benefit_fee: U128(benefit_fee),
+            payees: shares.into_iter().map(|(account_id, amount)| ReceiptShare{account_id, amount: U128(amount)}).collect(),
+            error: String::new(),
+        }
+    }
+
+    // Part of the attached deposit to charge for the quoted price, None if the deposit is short.
+    pub(crate) fn internal_charge_quote(&self, quote: &PriceQuote) -> Option<Balance> {
+        self.internal_charge(quote.price.0, quote.minimum_price.0, quote.storage_reserve.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const ONE_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+
+    fn asset_license(params: Option<&str>) -> AssetLicense {
+        AssetLicense {
+            sku_id: Some("sku".to_string()),
+            license_id: Some("license".to_string()),
+            title: "Personal".to_string(),
+            price: "2".to_string(),
+            currency: None,
+            active: Some(true),
+            hidden: None,
+            limited_edition: None,
+            sole_limit: None,
+            objects: None,
+            params: params.map(|x| x.to_string()),
+        }
+    }
+
+    fn set_deposit(deposit: Balance) {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(0)).attached_deposit(deposit).build());
+    }
+
+    #[test]
+    fn test_quote_deposit_is_charged() {
+        set_deposit(0);
+        let contract = Contract::new_default_meta(accounts(0), accounts(1));
+
+        //no slippage room: the quoted deposit is charged in full, a yocto less is rejected
+        let exact = asset_license(Some(r#"{"slippage_percents":0}"#));
+        let quote = contract.internal_quote(2 * ONE_NEAR, &exact, &SaleTerms::new(&exact, None, Vec::new()));
+        assert_eq!(quote.minimum_price.0, quote.price.0);
+        set_deposit(quote.deposit.0);
+        assert_eq!(contract.internal_charge_quote(&quote), Some(2 * ONE_NEAR));
+        set_deposit(quote.deposit.0 - 1);
+        assert_eq!(contract.internal_charge_quote(&quote), None);
+
+        //with slippage room a yocto less is charged off the price, the storage reserve is kept
+        let slippage = asset_license(Some(r#"{"slippage_percents":5}"#));
+        let quote = contract.internal_quote(2 * ONE_NEAR, &slippage, &SaleTerms::new(&slippage, None, Vec::new()));
+        set_deposit(quote.deposit.0);
+        assert_eq!(contract.internal_charge_quote(&quote), Some(2 * ONE_NEAR));
+        set_deposit(quote.deposit.0 - 1);
+        assert_eq!(contract.internal_charge_quote(&quote), Some(2 * ONE_NEAR - 1));
+        set_deposit(quote.minimum_price.0 + quote.storage_reserve.0 - 1);
+        assert_eq!(contract.internal_charge_quote(&quote), None);
+    }
+}
@@ -0,0 +1,266 @@
+use near_sdk::collections::Vector;
+use common_types::prices::Price;
+use common_types::types::{AssetLicense, NEAR_CURRENCY, Payee};
+use crate::*;
+use crate::internal::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptKind {
+    Mint,
+    UpdateLicense,
+    RenewLicense,
+}
+
+// Terms of a sale fixed when it is priced, passed along to the callback which charges it.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SaleTerms {
+    //SKU price and currency as listed by the asset
+    pub listed_price: String,
+    pub listed_currency: String,
+    //oracle NEAR/USD price the NEAR cost was calculated with
+    pub near_price: Option<String>,
+    pub payees: Vec<Payee>,
+}
+
+impl SaleTerms {
+    pub fn new(asset_license: &AssetLicense, near_price: Option<&Price>, payees: Vec<Payee>) -> Self {
+        Self {
+            listed_price: asset_license.price.clone(),
+            listed_currency: asset_license.currency.clone().unwrap_or(NEAR_CURRENCY.to_string()),
+            near_price: near_price.map(|x| x.string_price()),
+            payees,
+        }
+    }
+}
+
+// What the buyer paid for a sale.
+pub(crate) struct SalePayment {
+    //fungible token of the payment, NEAR if none
+    pub ft_token_id: Option<AccountId>,
+    pub amount: Balance,
+    pub refund: Balance,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReceiptShare {
+    pub account_id: AccountId,
+    pub amount: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Receipt {
+    pub receipt_id: u64,
+    pub kind: ReceiptKind,
+    pub token_id: TokenId,
+    pub buyer_id: AccountId,
+    pub asset_id: String,
+    pub inventory_id: String,
+    pub sku_id: String,
+    //currency of the payment, NEAR or the fungible token contract
+    pub currency: String,
+    pub listed_price: String,
+    pub listed_currency: String,
+    pub near_price: Option<String>,
+    //charged amount, in yoctoNEAR or in the smallest units of the token
+    pub amount: U128,
+    pub benefit_fee: U128,
+    pub benefit_account_id: Option<AccountId>,
+    pub payees: Vec<ReceiptShare>,
+    //part of the payment sent back to the buyer
+    pub refund: U128,
+    pub timestamp: u64, // Unix epoch in milliseconds
+    //when the buyer returned the license and got the amount back
+    pub refunded_at: Option<u64>,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn receipt(&self, receipt_id: u64) -> Option<Receipt> {
+        self.receipts.get(receipt_id)
+    }
+
+    pub fn receipts_total_supply(&self) -> u64 {
+        self.receipts.len()
+    }
+
+    //query the receipts of the buyer from the oldest one using pagination
+    pub fn receipts_for_buyer(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Receipt> {
+        self.receipts_page(self.receipts_per_buyer.get(&account_id.to_string()), from_index, limit)
+    }
+
+    //query the receipts of the token from the oldest one using pagination
+    pub fn receipts_for_token(&self, token_id: TokenId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Receipt> {
+        self.receipts_page(self.receipts_per_token.get(&token_id), from_index, limit)
+    }
+
+    //query the receipts of the asset from the oldest one using pagination
+    pub fn receipts_for_asset(&self, asset_id: String, from_index: Option<U128>, limit: Option<u64>) -> Vec<Receipt> {
+        self.receipts_page(self.receipts_per_asset.get(&asset_id), from_index, limit)
+    }
+
+    //query the receipts of the inventory from the oldest one using pagination
+    pub fn receipts_for_inventory(&self, inventory_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<Receipt> {
+        self.receipts_page(self.receipts_per_inventory.get(&inventory_id.to_string()), from_index, limit)
+    }
+}
+
+impl Contract {
+    fn receipts_page(&self, receipt_ids: Option<Vector<u64>>, from_index: Option<U128>, limit: Option<u64>) -> Vec<Receipt> {
+        let receipt_ids = match receipt_ids {
+            Some(receipt_ids) => receipt_ids,
+            None => return Vec::new(),
+        };
+        let start = u128::from(from_index.unwrap_or(U128(0)));
+
+        receipt_ids.iter()
+            .skip(start as usize)
+            .take(limit.unwrap_or(50) as usize)
+            .filter_map(|receipt_id| self.receipts.get(receipt_id))
+            .collect()
+    }
+
+    // Records the receipt of a sale and credits its proceeds to the ledger. Called before the
+    // storage is measured, so that the buyer pays for both; the sale is then either completed
+    // with internal_complete_sales or taken back with internal_rollback_sales.
+    pub(crate) fn internal_record_sale(
+        &mut self,
+        kind: ReceiptKind,
+        lic_token: &LicenseToken,
+        buyer_id: &AccountId,
+        terms: SaleTerms,
+        payment: SalePayment,
+    ) -> u64 {
+        let split = self.internal_split_sale(payment.amount, &terms.payees);
+        let receipt_id = self.internal_add_receipt(kind, lic_token, buyer_id, terms, payment, split);
+        let receipt = self.receipts.get(receipt_id).unwrap();
+        let ft_token_id = receipt_ft_token_id(&receipt);
+        for (account_id, credit) in receipt_credits(&receipt) {
+            self.internal_credit_proceeds(&account_id, &ft_token_id, credit);
+        }
+        receipt_id
+    }
+
+    // Sets the refund of the recorded sales, on the last receipt, and pays out their proceeds.
+    pub(crate) fn internal_complete_sales(&mut self, receipt_ids: &[u64], refund: Balance) {
+        let mut receipts: Vec<Receipt> = receipt_ids.iter().filter_map(|x| self.receipts.get(*x)).collect();
+        if let Some(receipt) = receipts.last_mut() {
+            receipt.refund = U128(refund);
+            self.receipts.replace(receipt.receipt_id, receipt);
+        }
+        self.internal_pay_revenue(&receipts);
+    }
+
+    // Takes back the sales recorded last, when the buyer can't pay for the storage.
+    pub(crate) fn internal_rollback_sales(&mut self, receipt_ids: &[u64]) {
+        for receipt_id in receipt_ids.iter().rev() {
+            if *receipt_id + 1 != self.receipts.len() {
+                env::panic_str("Only the last receipt can be rolled back")
+            }
+            let receipt = self.receipts.pop().unwrap();
+            let ft_token_id = receipt_ft_token_id(&receipt);
+            for (account_id, credit) in receipt_credits(&receipt) {
+                self.internal_debit_proceeds(&account_id, &ft_token_id, credit);
+            }
+            remove_receipt_from_index(&mut self.receipts_per_token, receipt.token_id, *receipt_id);
+            remove_receipt_from_index(&mut self.receipts_per_buyer, receipt.buyer_id.to_string(), *receipt_id);
+            remove_receipt_from_index(&mut self.receipts_per_asset, receipt.asset_id, *receipt_id);
+            remove_receipt_from_index(&mut self.receipts_per_inventory, receipt.inventory_id, *receipt_id);
+        }
+    }
+
+    // Records the receipt of a sale, split is the benefit fee and the shares of the payees.
+    pub(crate) fn internal_add_receipt(
+        &mut self,
+        kind: ReceiptKind,
+        lic_token: &LicenseToken,
+        buyer_id: &AccountId,
+        terms: SaleTerms,
+        payment: SalePayment,
+        split: (Balance, Vec<(AccountId, Balance)>),
+    ) -> u64 {
+        let (inventory_id, asset_id, _license_id, sku_id) = lic_token.inventory_asset_license_sku();
+        let receipt_id = self.receipts.len();
+        let (benefit_fee, shares) = split;
+        self.receipts.push(&Receipt {
+            receipt_id,
+            kind,
+            token_id: lic_token.token_id.clone(),
+            buyer_id: buyer_id.clone(),
+            asset_id: asset_id.clone(),
+            inventory_id: inventory_id.clone(),
+            sku_id,
+            currency: payment.ft_token_id.map(|x| x.to_string()).unwrap_or(NEAR_CURRENCY.to_string()),
+            listed_price: terms.listed_price,
+            listed_currency: terms.listed_currency,
+            near_price: terms.near_price,
+            amount: U128(payment.amount),
+            benefit_fee: U128(benefit_fee),
+            benefit_account_id: self.benefit_config.as_ref().filter(|_| benefit_fee != 0).map(|x| x.account_id.clone()),
+            payees: shares.into_iter().map(|(account_id, amount)| ReceiptShare{account_id, amount: U128(amount)}).collect(),
+            refund: U128(payment.refund),
+            timestamp: env::block_timestamp_ms(),
+            refunded_at: None,
+        });
+
+        add_receipt_to_index(&mut self.receipts_per_token, lic_token.token_id.clone(), receipt_id, |hash| {
+            StorageKey::ReceiptsPerTokenInner { token_id_hash: hash }
+        });
+        add_receipt_to_index(&mut self.receipts_per_buyer, buyer_id.to_string(), receipt_id, |hash| {
+            StorageKey::ReceiptsPerBuyerInner { account_id_hash: hash }
+        });
+        add_receipt_to_index(&mut self.receipts_per_asset, asset_id, receipt_id, |hash| {
+            StorageKey::ReceiptsPerAssetInner { asset_hash: hash }
+        });
+        add_receipt_to_index(&mut self.receipts_per_inventory, inventory_id, receipt_id, |hash| {
+            StorageKey::ReceiptsPerInventoryInner { inventory_hash: hash }
+        });
+        receipt_id
+    }
+}
+
+fn add_receipt_to_index(
+    index: &mut LookupMap<String, Vector<u64>>, key: String, receipt_id: u64, storage_key: impl Fn(CryptoHash) -> StorageKey,
+) {
+    let mut receipt_ids = index.get(&key).unwrap_or_else(|| {
+        Vector::new(storage_key(hash_id(&key)).try_to_vec().unwrap())
+    });
+    receipt_ids.push(&receipt_id);
+    index.insert(&key, &receipt_ids);
+}
+
+fn remove_receipt_from_index(index: &mut LookupMap<String, Vector<u64>>, key: String, receipt_id: u64) {
+    if let Some(mut receipt_ids) = index.get(&key) {
+        if !receipt_ids.is_empty() && receipt_ids.get(receipt_ids.len() - 1) == Some(receipt_id) {
+            receipt_ids.pop();
+        }
+        if receipt_ids.is_empty() {
+            index.remove(&key);
+        } else {
+            index.insert(&key, &receipt_ids);
+        }
+    }
+}
+
+// Accounts credited by the sale: the payees and the benefit account.
+pub(crate) fn receipt_credits(receipt: &Receipt) -> Vec<(AccountId, Balance)> {
+    let mut credits: Vec<(AccountId, Balance)> = receipt.payees.iter()
+        .map(|x| (x.account_id.clone(), x.amount.0))
+        .collect();
+    if let Some(benefit_account_id) = receipt.benefit_account_id.as_ref() {
+        credits.push((benefit_account_id.clone(), receipt.benefit_fee.0));
+    }
+    credits.into_iter().filter(|(_, credit)| *credit != 0).collect()
+}
+
+pub(crate) fn receipt_ft_token_id(receipt: &Receipt) -> Option<AccountId> {
+    if receipt.currency == NEAR_CURRENCY {
+        None
+    } else {
+        Some(AccountId::new_unchecked(receipt.currency.clone()))
+    }
+}
@@ -0,0 +1,223 @@
+use common_types::utils::assert_one_yocto;
+use crate::*;
+use crate::receipts::receipt_ft_token_id;
+
+#[near_bindgen]
+impl Contract {
+    // Returns the license within the refund window of its SKU: burns the token and sends the
+    // charged amounts back to the buyer, taken from the proceeds of the payees or their refund escrow.
+    #[payable]
+    pub fn nft_request_refund(&mut self, token_id: TokenId, memo: Option<String>) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let token = self.tokens_by_id.get(&token_id).expect("No token");
+        if account_id != token.owner_id {
+            env::panic_str("Only the token owner can request a refund")
+        }
+        let deadline = token.metadata.refund_deadline().unwrap_or_else(|| env::panic_str("License is not refundable"));
+        let now = env::block_timestamp_ms();
+        if now > deadline {
+            env::panic_str("Refund window is over")
+        }
+
+        let receipts = self.internal_refundable_receipts(&token);
+        if receipts.is_empty() {
+            env::panic_str("Nothing to refund")
+        }
+
+        let mut refunds: Vec<(Option<AccountId>, Balance)> = Vec::new();
+        for receipt in receipts.iter() {
+            let ft_token_id = receipt_ft_token_id(receipt);
+            for share in receipt.payees.iter() {
+                self.internal_debit_refund(&share.account_id, &ft_token_id, share.amount.0);
+            }
+            if let Some(benefit_account_id) = receipt.benefit_account_id.as_ref() {
+                self.internal_debit_refund(benefit_account_id, &ft_token_id, receipt.benefit_fee.0);
+            }
+            self.receipts.replace(receipt.receipt_id, &Receipt{refunded_at: Some(now), ..receipt.clone()});
+
+            match refunds.iter_mut().find(|(x, _)| *x == ft_token_id) {
+                Some((_, amount)) => *amount += receipt.amount.0,
+                None => refunds.push((ft_token_id, receipt.amount.0)),
+            }
+        }
+
+        //burning releases the license quota of the asset
+        self.internal_burn(&token_id, None, Some("refund".to_string()));
+
+        for (ft_token_id, amount) in refunds.into_iter().filter(|(_, amount)| *amount > 0) {
+            self.internal_credit_proceeds(&account_id, &ft_token_id, amount);
+            self.internal_payout(account_id.clone(), ft_token_id, amount);
+        }
+
+        let nft_refund_log: EventLog = EventLog {
+            standard: NFT_LICENSE_STANDARD_NAME.to_string(),
+            version: NFT_LICENSE_SPEC.to_string(),
+            event: EventLogVariant::NftRefund(vec![NftRefundLog {
+                owner_id: account_id.to_string(),
+                token_ids: vec![token_id],
+                receipt_ids: receipts.iter().map(|x| x.receipt_id).collect(),
+                memo,
+            }]),
+        };
+        self.log_event(&nft_refund_log.to_string());
+    }
+
+    // Funds of the account kept to cover refunds of proceeds it has already withdrawn.
+    pub fn refund_escrow_of(&self, account_id: AccountId, ft_token_id: Option<AccountId>) -> U128 {
+        U128(self.refund_escrow.get(&(account_id, ft_token_id)).unwrap_or(0))
+    }
+
+    // Adds the attached deposit to the NEAR refund escrow of the caller,
+    // fungible tokens are added with ft_transfer_call and the refund_escrow message.
+    #[payable]
+    pub fn deposit_refund_escrow(&mut self) -> U128 {
+        let amount = env::attached_deposit();
+        if amount == 0 {
+            env::panic_str("Requires attached deposit")
+        }
+        U128(self.internal_credit_refund_escrow(&env::predecessor_account_id(), &None, amount))
+    }
+
+    // Sends the refund escrow back to the caller, all of it if the amount is not set.
+    #[payable]
+    pub fn withdraw_refund_escrow(&mut self, ft_token_id: Option<AccountId>, amount: Option<U128>) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let key = (account_id.clone(), ft_token_id.clone());
+        let balance = self.refund_escrow.get(&key).unwrap_or(0);
+        let amount = amount.map(|x| x.0).unwrap_or(balance);
+        if amount == 0 {
+            env::panic_str("Nothing to withdraw")
+        }
+        if amount > balance {
+            env::panic_str(&format!("Not enough refund escrow, {} available", balance))
+        }
+        if balance == amount {
+            self.refund_escrow.remove(&key);
+        } else {
+            self.refund_escrow.insert(&key, &(balance - amount));
+        }
+
+        //paid out through the proceeds ledger, so a failed transfer stays claimable with withdraw
+        self.internal_credit_proceeds(&account_id, &ft_token_id, amount);
+        self.internal_payout(account_id, ft_token_id, amount)
+    }
+}
+
+impl Contract {
+    // Unrefunded receipts of the owner for the current token, the receipts of an earlier
+    // burned token with the same ID are older than the token.
+    fn internal_refundable_receipts(&self, token: &Token) -> Vec<Receipt> {
+        let issued_at = token.metadata.issued_at.unwrap_or(0);
+        let receipt_ids = match self.receipts_per_token.get(&token.token_id) {
+            Some(receipt_ids) => receipt_ids,
+            None => return Vec::new(),
+        };
+        receipt_ids.iter()
+            .filter_map(|receipt_id| self.receipts.get(receipt_id))
+            .filter(|x| x.buyer_id == token.owner_id && x.refunded_at.is_none() && x.timestamp >= issued_at)
+            .collect()
+    }
+
+    pub(crate) fn internal_credit_refund_escrow(&mut self, account_id: &AccountId, ft_token_id: &Option<AccountId>, amount: Balance) -> Balance {
+        let key = (account_id.clone(), ft_token_id.clone());
+        let balance = self.refund_escrow.get(&key).unwrap_or(0) + amount;
+        self.refund_escrow.insert(&key, &balance);
+        balance
+    }
+
+    // Takes the refunded share of the account off its proceeds first, then off its refund escrow.
+    fn internal_debit_refund(&mut self, account_id: &AccountId, ft_token_id: &Option<AccountId>, amount: Balance) {
+        let key = (account_id.clone(), ft_token_id.clone());
+        let proceeds = self.proceeds.get(&key).unwrap_or(0);
+        let escrow = self.refund_escrow.get(&key).unwrap_or(0);
+        if proceeds + escrow < amount {
+            env::panic_str(&format!("Refund of {} is not covered by {}", amount, account_id))
+        }
+
+        let from_proceeds = std::cmp::min(proceeds, amount);
+        if from_proceeds == proceeds {
+            self.proceeds.remove(&key);
+        } else {
+            self.proceeds.insert(&key, &(proceeds - from_proceeds));
+        }
+        let from_escrow = amount - from_proceeds;
+        if from_escrow > 0 {
+            if from_escrow == escrow {
+                self.refund_escrow.remove(&key);
+            } else {
+                self.refund_escrow.insert(&key, &(escrow - from_escrow));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::collections::Vector;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use common_types::types::NEAR_CURRENCY;
+    use crate::internal::hash_id;
+
+    fn add_receipt(contract: &mut Contract, token_id: &TokenId, buyer_id: AccountId, timestamp: u64) {
+        let receipt_id = contract.receipts.len();
+        contract.receipts.push(&Receipt {
+            receipt_id,
+            kind: ReceiptKind::Mint,
+            token_id: token_id.clone(),
+            buyer_id,
+            asset_id: "asset".to_string(),
+            inventory_id: accounts(1).to_string(),
+            sku_id: "sku".to_string(),
+            currency: NEAR_CURRENCY.to_string(),
+            listed_price: "1".to_string(),
+            listed_currency: NEAR_CURRENCY.to_string(),
+            near_price: None,
+            amount: U128(100),
+            benefit_fee: U128(0),
+            benefit_account_id: None,
+            payees: Vec::new(),
+            refund: U128(0),
+            timestamp,
+            refunded_at: None,
+        });
+        let mut receipt_ids = contract.receipts_per_token.get(token_id).unwrap_or_else(|| {
+            Vector::new(StorageKey::ReceiptsPerTokenInner { token_id_hash: hash_id(token_id) }.try_to_vec().unwrap())
+        });
+        receipt_ids.push(&receipt_id);
+        contract.receipts_per_token.insert(token_id, &receipt_ids);
+    }
+
+    #[test]
+    fn test_refundable_receipts_of_reused_token_id() {
+        testing_env!(VMContextBuilder::new().predecessor_account_id(accounts(0)).build());
+        let mut contract = Contract::new_default_meta(accounts(0), accounts(1));
+        let token_id: TokenId = "1".to_string();
+
+        //bought and burned before the token ID was minted again
+        add_receipt(&mut contract, &token_id, accounts(2), 1_000);
+        //the current token
+        add_receipt(&mut contract, &token_id, accounts(2), 5_000);
+        add_receipt(&mut contract, &token_id, accounts(3), 6_000);
+
+        let token = Token {
+            token_id: token_id.clone(),
+            owner_id: accounts(2),
+            asset_id: "asset".to_string(),
+            license: None,
+            metadata: TokenMetadata { issued_at: Some(5_000), ..Default::default() },
+            approved_account_ids: Default::default(),
+            next_approval_id: 0,
+            royalty: Default::default(),
+        };
+        let receipt_ids: Vec<u64> = contract.internal_refundable_receipts(&token).iter().map(|x| x.receipt_id).collect();
+        assert_eq!(receipt_ids, vec![1]);
+
+        let refunded = Receipt{refunded_at: Some(7_000), ..contract.receipts.get(1).unwrap()};
+        contract.receipts.replace(1, &refunded);
+        assert!(contract.internal_refundable_receipts(&token).is_empty());
+    }
+}
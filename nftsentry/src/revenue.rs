@@ -0,0 +1,64 @@
+use common_types::types::{Payee, split_revenue};
+use crate::receipts::{receipt_credits, receipt_ft_token_id};
+use crate::*;
+
+impl Contract {
+    // Benefit fee and the shares of the payees of a sale.
+    pub(crate) fn internal_split_sale(&self, amount: Balance, payees: &[Payee]) -> (Balance, Vec<(AccountId, Balance)>) {
+        let (base_amount, benefit_fee) = self.split_fees(amount);
+        (benefit_fee, split_revenue(base_amount, payees))
+    }
+
+    // Pays the proceeds of the recorded sales, credited to the ledger by internal_record_sale:
+    // the benefit fee of every sale goes to the benefit account, the rest is split between
+    // the payees of the sold asset. Every account gets a single transfer if the proceeds are
+    // paid out automatically. The sales are in the same currency.
+    pub(crate) fn internal_pay_revenue(&mut self, receipts: &[Receipt]) {
+        let ft_token_id = match receipts.first() {
+            Some(receipt) => receipt_ft_token_id(receipt),
+            None => return,
+        };
+        let mut amount: Balance = 0;
+        let mut benefit_fee: Balance = 0;
+        let mut shares: Vec<(AccountId, Balance)> = Vec::new();
+        let mut credits: Vec<(AccountId, Balance)> = Vec::new();
+        for receipt in receipts {
+            amount += receipt.amount.0;
+            benefit_fee += receipt.benefit_fee.0;
+            for share in receipt.payees.iter() {
+                match shares.iter_mut().find(|x| x.0 == share.account_id) {
+                    Some(x) => x.1 += share.amount.0,
+                    None => shares.push((share.account_id.clone(), share.amount.0)),
+                }
+            }
+            for (account_id, credit) in receipt_credits(receipt) {
+                match credits.iter_mut().find(|x| x.0 == account_id) {
+                    Some(x) => x.1 += credit,
+                    None => credits.push((account_id, credit)),
+                }
+            }
+        }
+
+        if self.settings.auto_payout {
+            for (account_id, credit) in credits {
+                self.internal_payout(account_id, ft_token_id.clone(), credit);
+            }
+        }
+
+        let nft_revenue_split_log: EventLog = EventLog {
+            standard: NFT_LICENSE_STANDARD_NAME.to_string(),
+            version: NFT_LICENSE_SPEC.to_string(),
+            event: EventLogVariant::NftRevenueSplit(vec![NftRevenueSplitLog {
+                ft_token_id: ft_token_id.map(|x| x.to_string()),
+                amount: U128(amount),
+                benefit_fee: U128(benefit_fee),
+                payees: shares.into_iter().map(|(account_id, share)| NftRevenueShareLog {
+                    account_id: account_id.to_string(),
+                    amount: U128(share),
+                }).collect(),
+                memo: None,
+            }]),
+        };
+        self.log_event(&nft_revenue_split_log.to_string());
+    }
+}
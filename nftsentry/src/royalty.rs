@@ -1,6 +1,7 @@
 use crate::*;
+use crate::internal::*;
 
-pub trait NonFungibleTokenCore {
+pub trait NonFungibleTokenPayout {
     //calculates the payout for a token given the passed in balance. This is a view method
     fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: u32) -> Payout;
     
@@ -16,111 +17,101 @@ pub trait NonFungibleTokenCore {
     ) -> Payout;
 }
 
-// #[near_bindgen]
-// impl NonFungibleTokenCore for Contract {
-//
-//     //calculates the payout for a token given the passed in balance. This is a view method
-//     fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: u32) -> Payout {
-//         //get the token object
-// 		let token = self.tokens_by_id.get(&token_id).expect("No token");
-//
-//         //get the owner of the token
-//         let owner_id = token.owner_id;
-//         //keep track of the total perpetual royalties
-//         let mut total_perpetual = 0;
-//         //get the u128 version of the passed in balance (which was U128 before)
-//         let balance_u128 = u128::from(balance);
-// 		//keep track of the payout object to send back
-//         let mut payout_object = Payout {
-//             payout: HashMap::new()
-//         };
-//         //get the royalty object from token
-// 		// let royalty = token.royalty;
-//
-//         //make sure we're not paying out to too many people (GAS limits this)
-// 		// assert!(royalty.len() as u32 <= max_len_payout, "Market cannot payout to that many receivers");
-//
-//         //go through each key and value in the royalty object
-// 		for (k, v) in royalty.iter() {
-//             //get the key
-// 			let key = k.clone();
-//             //only insert into the payout if the key isn't the token owner (we add their payout at the end)
-// 			if key != owner_id {
-//                 //
-// 				payout_object.payout.insert(key, royalty_to_payout(*v, balance_u128));
-// 				total_perpetual += *v;
-// 			}
-// 		}
-//
-// 		// payout to previous owner who gets 100% - total perpetual royalties
-// 		payout_object.payout.insert(owner_id, royalty_to_payout(10000 - total_perpetual, balance_u128));
-//
-//         //return the payout object
-// 		payout_object
-// 	}
-//
-//     //transfers the token to the receiver ID and returns the payout object that should be payed given the passed in balance.
-//     #[payable]
-//     fn nft_transfer_payout(
-//         &mut self,
-//         receiver_id: AccountId,
-//         token_id: TokenId,
-//         approval_id: u64,
-//         memo: Option<String>,
-//         balance: U128,
-//         max_len_payout: u32,
-//     ) -> Payout {
-//         //assert that the user attached 1 yocto NEAR for security reasons
-//         assert_one_yocto();
-//         //get the sender ID
-//         let sender_id = env::predecessor_account_id();
-//         //transfer the token to the passed in receiver and get the previous token object back
-//         let previous_token = self.internal_transfer(
-//             &sender_id,
-//             &receiver_id,
-//             &token_id,
-//             Some(approval_id),
-//             memo,
-//         );
-//
-//         //refund the previous token owner for the storage used up by the previous approved account IDs
-//         refund_approved_account_ids(
-//             previous_token.owner_id.clone(),
-//             &previous_token.approved_account_ids,
-//         );
-//
-//         //get the owner of the token
-//         let owner_id = previous_token.owner_id;
-//         //keep track of the total perpetual royalties
-//         let mut total_perpetual = 0;
-//         //get the u128 version of the passed in balance (which was U128 before)
-//         let balance_u128 = u128::from(balance);
-// 		//keep track of the payout object to send back
-//         let mut payout_object = Payout {
-//             payout: HashMap::new()
-//         };
-//         //get the royalty object from token
-// 		let royalty = previous_token.royalty;
-//
-//         //make sure we're not paying out to too many people (GAS limits this)
-// 		assert!(royalty.len() as u32 <= max_len_payout, "Market cannot payout to that many receivers");
-//
-//         //go through each key and value in the royalty object
-// 		for (k, v) in royalty.iter() {
-//             //get the key
-// 			let key = k.clone();
-//             //only insert into the payout if the key isn't the token owner (we add their payout at the end)
-// 			if key != owner_id {
-//                 //
-// 				payout_object.payout.insert(key, royalty_to_payout(*v, balance_u128));
-// 				total_perpetual += *v;
-// 			}
-// 		}
-//
-// 		// payout to previous owner who gets 100% - total perpetual royalties
-// 		payout_object.payout.insert(owner_id, royalty_to_payout(10000 - total_perpetual, balance_u128));
-//
-//         //return the payout object
-// 		payout_object
-//     }
-// }
\ No newline at end of file
+//split the balance between the license transfer fee receiver, the royalty receivers
+//and the owner (who gets 100% - total perpetual royalties of the rest)
+fn royalty_payout(
+    owner_id: AccountId,
+    royalty: &HashMap<AccountId, u32>,
+    fee: Option<(AccountId, Balance)>,
+    balance: U128,
+    max_len_payout: u32,
+) -> Payout {
+    //keep track of the total perpetual royalties
+    let mut total_perpetual = 0;
+    //get the u128 version of the passed in balance (which was U128 before)
+    let mut balance_u128 = u128::from(balance);
+    //keep track of the payout object to send back
+    let mut payout_object = Payout {
+        payout: HashMap::new()
+    };
+
+    //make sure we're not paying out to too many people (GAS limits this)
+    let payout_len = royalty.len() + fee.is_some() as usize;
+    assert!(payout_len as u32 <= max_len_payout, "Market cannot payout to that many receivers");
+
+    //the license transfer fee is taken from the balance first
+    if let Some((fee_receiver_id, fee)) = fee {
+        assert!(balance_u128 >= fee, "Balance is less than license transfer fee of {} yoctoNEAR", fee);
+        balance_u128 -= fee;
+        payout_object.payout.insert(fee_receiver_id, U128(fee));
+    }
+
+    //go through each key and value in the royalty object
+    for (k, v) in royalty.iter() {
+        //only insert into the payout if the key isn't the token owner (we add their payout at the end)
+        if k != &owner_id {
+            let amount = royalty_to_payout(*v, balance_u128).0 + payout_object.payout.get(k).map_or(0, |x| x.0);
+            payout_object.payout.insert(k.clone(), U128(amount));
+            total_perpetual += *v;
+        }
+    }
+
+    // payout to previous owner who gets 100% - total perpetual royalties
+    let amount = royalty_to_payout(10000 - total_perpetual, balance_u128).0 + payout_object.payout.get(&owner_id).map_or(0, |x| x.0);
+    payout_object.payout.insert(owner_id, U128(amount));
+
+    //return the payout object
+    payout_object
+}
+
+#[near_bindgen]
+impl NonFungibleTokenPayout for Contract {
+
+    //calculates the payout for a token given the passed in balance. This is a view method
+    fn nft_payout(&self, token_id: TokenId, balance: U128, max_len_payout: u32) -> Payout {
+        //get the token object
+        let token = self.tokens_by_id.get(&token_id).expect("No token");
+
+        let fee = self.transfer_fee(&token);
+        royalty_payout(token.owner_id, &token.royalty, fee, balance, max_len_payout)
+    }
+
+    //transfers the token to the receiver ID and returns the payout object that should be payed given the passed in balance.
+    #[payable]
+    fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: u64,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: u32,
+    ) -> Payout {
+        //assert that the user attached 1 yocto NEAR for security reasons
+        assert_one_yocto();
+        //get the sender ID
+        let sender_id = env::predecessor_account_id();
+        //transfer the token to the passed in receiver and get the previous token object back
+        let initial_storage_usage = env::storage_usage();
+        let previous_token = self.internal_transfer(
+            &sender_id,
+            &receiver_id,
+            &token_id,
+            Some(approval_id),
+            memo,
+        );
+        //the owner pays for the storage of the transfer and its history entry
+        if let Err(msg) = self.internal_charge_storage(&previous_token.owner_id, initial_storage_usage) {
+            env::panic_str(&msg)
+        }
+
+        //refund the previous token owner for the storage used up by the previous approved account IDs
+        refund_approved_account_ids(
+            previous_token.owner_id.clone(),
+            &previous_token.approved_account_ids,
+        );
+
+        let fee = self.transfer_fee(&previous_token);
+        royalty_payout(previous_token.owner_id, &previous_token.royalty, fee, balance, max_len_payout)
+    }
+}
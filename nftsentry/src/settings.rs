@@ -0,0 +1,106 @@
+use common_types::types::AssetLicense;
+use common_types::utils::balance_from_string;
+use crate::*;
+
+pub const DEFAULT_SLIPPAGE_PERCENTS: u32 = 3;
+pub const DEFAULT_STORAGE_RESERVE: &str = "0.1";
+
+// Owner-managed contract settings.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ContractSettings {
+    // how much less than the NEAR price of a SKU a buyer may pay when the NEAR rate moves,
+    // a SKU can override it with its slippage_percents param
+    pub slippage_percents: u32,
+    // part of the attached deposit kept for the storage of a minted or updated token
+    pub storage_reserve: U128,
+    // transfer the sale proceeds right away instead of keeping them until withdraw
+    #[serde(default)]
+    pub auto_payout: bool,
+}
+
+impl Default for ContractSettings {
+    fn default() -> Self {
+        Self {
+            slippage_percents: DEFAULT_SLIPPAGE_PERCENTS,
+            storage_reserve: U128(balance_from_string(DEFAULT_STORAGE_RESERVE.to_string())),
+            auto_payout: false,
+        }
+    }
+}
+
+pub(crate) fn minimum_price(price: Balance, slippage_percents: u32) -> Balance {
+    price * (100 - slippage_percents.min(100)) as u128 / 100
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_contract_settings(&mut self, settings: ContractSettings) {
+        if env::predecessor_account_id() != self.owner_id {
+            env::panic_str("Unauthorized")
+        }
+        if settings.slippage_percents > 100 {
+            env::panic_str("Slippage cannot be greater than 100%")
+        }
+        self.settings = settings;
+        self.log_settings();
+    }
+
+    pub fn contract_settings(&self) -> ContractSettings {
+        self.settings.clone()
+    }
+
+    pub fn set_benefit_config(&mut self, benefit_config: Option<BenefitConfig>) {
+        if env::predecessor_account_id() != self.owner_id {
+            env::panic_str("Unauthorized")
+        }
+        if let Some(config) = benefit_config.as_ref() {
+            if config.fee_milli_percent_amount > 100_000 {
+                env::panic_str("Benefit fee cannot be greater than 100%")
+            }
+            if config.royalty.unwrap_or(0) > 10_000 {
+                env::panic_str("Benefit royalty cannot be greater than 100%")
+            }
+        }
+        self.benefit_config = benefit_config;
+        self.log_settings();
+    }
+}
+
+impl Contract {
+    pub(crate) fn sku_slippage_percents(&self, asset_license: &AssetLicense) -> u32 {
+        asset_license.get_params().slippage_percents.unwrap_or(self.settings.slippage_percents)
+    }
+
+    // Part of the attached deposit to charge for the price: the price itself if the deposit covers it
+    // and the storage reserve, otherwise the rest of the deposit if it is not less than the minimum price.
+    pub(crate) fn internal_charge(&self, price: Balance, minimum_price: Balance, storage_reserve: Balance) -> Option<Balance> {
+        let deposit = env::attached_deposit();
+        if deposit >= price + storage_reserve {
+            return Some(price)
+        }
+        let reserved_price = deposit.saturating_sub(storage_reserve);
+        if reserved_price < minimum_price {
+            None
+        } else {
+            Some(reserved_price)
+        }
+    }
+
+    fn log_settings(&self) {
+        let nft_update_settings_log: EventLog = EventLog {
+            standard: NFT_LICENSE_STANDARD_NAME.to_string(),
+            version: NFT_LICENSE_SPEC.to_string(),
+            event: EventLogVariant::NftUpdateSettings(vec![NftUpdateSettingsLog {
+                owner_id: self.owner_id.to_string(),
+                slippage_percents: self.settings.slippage_percents,
+                storage_reserve: self.settings.storage_reserve,
+                auto_payout: self.settings.auto_payout,
+                benefit_account_id: self.benefit_config.as_ref().map(|x| x.account_id.to_string()),
+                benefit_fee_milli_percent: self.benefit_config.as_ref().map(|x| x.fee_milli_percent_amount),
+                memo: None,
+            }]),
+        };
+        self.log_event(&nft_update_settings_log.to_string());
+    }
+}
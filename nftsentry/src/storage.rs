@@ -0,0 +1,119 @@
+use common_types::utils::{assert_one_yocto, format_balance};
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    // NEAR balance of the account that pays the storage of its purchases in fungible tokens.
+    pub fn storage_balance_of(&self, account_id: AccountId) -> U128 {
+        U128(self.storage_deposits.get(&account_id).unwrap_or(0))
+    }
+
+    // Adds the attached deposit to the storage balance of the account, the caller by default.
+    // The first deposit of an account pays for its own entry.
+    #[payable]
+    pub fn deposit_storage(&mut self, account_id: Option<AccountId>) -> U128 {
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let initial_storage_usage = env::storage_usage();
+        let balance = self.storage_deposits.get(&account_id).unwrap_or(0);
+        self.storage_deposits.insert(&account_id, &balance);
+
+        let entry_cost = env::storage_byte_cost() * Balance::from(env::storage_usage() - initial_storage_usage);
+        let amount = env::attached_deposit();
+        if amount <= entry_cost {
+            env::panic_str(&format!("Must attach more than {} NEAR", format_balance(entry_cost)))
+        }
+        let balance = balance + amount - entry_cost;
+        self.storage_deposits.insert(&account_id, &balance);
+        U128(balance)
+    }
+
+    // Sends the storage balance back to the caller, all of it if the amount is not set.
+    #[payable]
+    pub fn withdraw_storage(&mut self, amount: Option<U128>) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self.storage_deposits.get(&account_id).unwrap_or(0);
+        let amount = amount.map(|x| x.0).unwrap_or(balance);
+        if amount == 0 {
+            env::panic_str("Nothing to withdraw")
+        }
+        if amount > balance {
+            env::panic_str(&format!("Not enough storage balance, {} available", balance))
+        }
+        if balance == amount {
+            self.storage_deposits.remove(&account_id);
+        } else {
+            self.storage_deposits.insert(&account_id, &(balance - amount));
+        }
+        Promise::new(account_id).transfer(amount)
+    }
+}
+
+impl Contract {
+    // Takes the storage used since initial_storage off the storage balance of the account,
+    // returns the charged amount. Leaves the balance as is if it doesn't cover the storage.
+    pub(crate) fn internal_charge_storage(&mut self, account_id: &AccountId, initial_storage: u64) -> Result<Balance, String> {
+        self.internal_charge_storage_cost(account_id, storage_cost_since(initial_storage))
+    }
+
+    pub(crate) fn internal_charge_storage_cost(&mut self, account_id: &AccountId, cost: Balance) -> Result<Balance, String> {
+        if cost == 0 {
+            return Ok(0)
+        }
+        let balance = self.storage_deposits.get(account_id).unwrap_or(0);
+        if cost > balance {
+            return Err(format!(
+                "Storage balance of {} NEAR doesn't cover {} NEAR of storage, add it with deposit_storage",
+                format_balance(balance), format_balance(cost),
+            ))
+        }
+        self.storage_deposits.insert(account_id, &(balance - cost));
+        Ok(cost)
+    }
+
+    // Gives back storage charged upfront and not used.
+    pub(crate) fn internal_credit_storage(&mut self, account_id: &AccountId, amount: Balance) {
+        if amount == 0 {
+            return
+        }
+        let balance = self.storage_deposits.get(account_id).unwrap_or(0);
+        self.storage_deposits.insert(account_id, &(balance + amount));
+    }
+}
+
+// Cost of the storage used since initial_storage, zero if it didn't grow.
+pub(crate) fn storage_cost_since(initial_storage: u64) -> Balance {
+    env::storage_byte_cost() * Balance::from(env::storage_usage().saturating_sub(initial_storage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    const ONE_NEAR: Balance = 1_000_000_000_000_000_000_000_000;
+
+    #[test]
+    fn test_charge_storage() {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_NEAR)
+            .build());
+        let mut contract = Contract::new_default_meta(accounts(0), accounts(1));
+        let balance = contract.deposit_storage(None).0;
+        assert!(balance > 0 && balance < ONE_NEAR);
+
+        let storage_usage = env::storage_usage();
+        assert_eq!(contract.internal_charge_storage(&accounts(2), storage_usage), Ok(0));
+        assert_eq!(contract.storage_balance_of(accounts(2)).0, balance);
+
+        // 1 KB is 0.01 NEAR
+        assert_eq!(contract.internal_charge_storage(&accounts(2), storage_usage - 1000), Ok(ONE_NEAR / 100));
+        assert_eq!(contract.storage_balance_of(accounts(2)).0, balance - ONE_NEAR / 100);
+
+        // not covered, the balance is kept
+        assert!(contract.internal_charge_storage(&accounts(2), storage_usage - 100_000).is_err());
+        assert_eq!(contract.storage_balance_of(accounts(2)).0, balance - ONE_NEAR / 100);
+    }
+}
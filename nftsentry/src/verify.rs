@@ -0,0 +1,86 @@
+use common_types::types::LicenseGeneral;
+use crate::*;
+use crate::internal::owner_asset_key;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseUsage {
+    Commercial,
+    Personal,
+    Exclusive,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VerifiedLicense {
+    pub token_id: TokenId,
+    pub sku_id: String,
+    // objects covered by the license, the whole asset if empty
+    pub objects: Vec<String>,
+    pub license: TokenLicense,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LicenseVerification {
+    pub valid: bool,
+    pub token_ids: Vec<TokenId>,
+    pub licenses: Vec<VerifiedLicense>,
+}
+
+#[near_bindgen]
+impl Contract {
+    // Checks whether the account holds a license for the usage of the asset (or one of its objects)
+    // which is valid at the given time, now by default. Commercial licenses cover personal usage too.
+    pub fn nft_verify_license(
+        &self,
+        account_id: AccountId,
+        asset_id: AssetId,
+        usage: LicenseUsage,
+        object_id: Option<String>,
+        at: Option<u64>,
+    ) -> LicenseVerification {
+        let at = at.unwrap_or_else(env::block_timestamp_ms);
+        let mut licenses: Vec<VerifiedLicense> = Vec::new();
+
+        let key = owner_asset_key(&account_id, &asset_id);
+        let token_ids = self.tokens_per_owner_asset.get(&key).map(|x| x.to_vec()).unwrap_or_default();
+        for token_id in token_ids {
+            let token = match self.nft_token(token_id) {
+                Some(token) => token,
+                None => continue,
+            };
+            let license = match token.license.as_ref() {
+                Some(license) if license.is_valid_at(at) => license.clone(),
+                _ => continue,
+            };
+            let matches_usage = match usage {
+                LicenseUsage::Commercial => token.is_commercial(),
+                LicenseUsage::Personal => token.is_personal() || token.is_commercial(),
+                LicenseUsage::Exclusive => token.is_exclusive(),
+            };
+            if !matches_usage {
+                continue
+            }
+            let objects = token.objects();
+            if let Some(object_id) = object_id.as_ref() {
+                if !objects.is_empty() && !objects.contains(object_id) {
+                    continue
+                }
+            }
+            licenses.push(VerifiedLicense {
+                token_id: token.token_id.clone(),
+                sku_id: token.sku_id(),
+                objects,
+                license,
+            });
+        }
+
+        LicenseVerification {
+            valid: !licenses.is_empty(),
+            token_ids: licenses.iter().map(|x| x.token_id.clone()).collect(),
+            licenses,
+        }
+    }
+}
@@ -1,15 +1,17 @@
 use std::collections::{BTreeMap, HashMap};
 use std::string::ToString;
-use near_sdk::serde_json;
+use near_sdk::{env, serde_json, AccountId};
 use minijinja::value::{Value};
+use minijinja::{Error, State};
 
 use crate::*;
 use common_types::types::*;
-use crate::types::SKUAvailability;
+use crate::types::{AssetChangeReport, LicenseViolation, SKUAvailability, SKUAvailabilityChange};
 
 pub const LEVEL_INVENTORY: &str = "inventory";
 pub const LEVEL_LICENSES: &str = "licenses";
 pub const DEFAULT_TEMPLATE: &str = "true";
+const MS_IN_DAY: u64 = 24 * 60 * 60 * 1000;
 
 pub trait ConfigInterface {
     fn check_transition(
@@ -26,19 +28,27 @@ pub trait ConfigInterface {
     fn list_available(
         &self, inventory: FullInventory, policy_rules: Option<Vec<Limitation>>,
         upgrade_rules: Option<Vec<Policy>>) -> Vec<SKUAvailability>;
+    fn simulate_asset_change(
+        &self, inventory: FullInventory, new_asset_licenses: Option<Vec<AssetLicense>>,
+        new_policy_rules: Option<Vec<Limitation>>) -> AssetChangeReport;
     // fn clone_with_additional(&self, l: Vec<Limitation>) -> AllPolicies;
 }
 
 pub fn init_policies() -> AllPolicies {
     let raw = include_bytes!("rules.json").to_vec();
-    let mut config: AllPolicies = serde_json::from_slice(raw.as_slice()).expect("Fail to parse rules.yaml");
+    parse_policies(raw.as_slice()).expect("Fail to parse rules.yaml")
+}
+
+// Parses rules in the rules.json format.
+pub fn parse_policies(raw: &[u8]) -> Result<AllPolicies, String> {
+    let mut config: AllPolicies = serde_json::from_slice(raw).map_err(|e| e.to_string())?;
 
     for (policy_name, pol) in &mut config.policies {
         // config.policies.get_mut(policy_name.as_str()).unwrap().name = Some(policy_name.clone());
         pol.name = Some(policy_name.clone());
     }
 
-    config
+    Ok(config)
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
@@ -78,6 +88,15 @@ pub struct LimitsInfo {
     pub issued: i32,
 }
 
+// Base policy set registered by an inventory, applied on top of the global
+// policies and below the per-asset rules.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct InventoryPolicies {
+    pub policy_rules: Option<Vec<Limitation>>,
+    pub upgrade_rules: Option<Vec<Policy>>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Context {
@@ -91,7 +110,7 @@ impl Policy {
 }
 
 pub trait LimitCheck {
-    fn check(&self, matched: Vec<&dyn LicenseGeneral>, l: &Limitation, ctx: Context) -> IsAvailableResponse;
+    fn check(&self, matched: Vec<&dyn LicenseGeneral>, l: &Limitation, ctx: &Context) -> IsAvailableResponse;
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -120,6 +139,7 @@ pub struct FutureStateOpt {
 pub struct Limitation {
     pub name: String,
     pub display_name: Option<String>,
+    #[serde(default)]
     pub scope: String,
     pub level: String,
     pub template: String,
@@ -135,7 +155,7 @@ pub struct MaxCount {
 }
 
 impl LimitCheck for MaxCount {
-    fn check(&self, matched: Vec<&dyn LicenseGeneral>, l: &Limitation, _: Context) -> IsAvailableResponse {
+    fn check(&self, matched: Vec<&dyn LicenseGeneral>, l: &Limitation, _: &Context) -> IsAvailableResponse {
         let name = if l.display_name.is_none() { l.name.clone() } else { l.display_name.as_ref().unwrap().clone() };
         if matched.len() > self.count as usize {
             let msg = format!(
@@ -163,7 +183,7 @@ impl LimitCheck for MaxCount {
 pub struct Exclusive {}
 
 impl LimitCheck for Exclusive {
-    fn check(&self, matched: Vec<&dyn LicenseGeneral>, l: &Limitation, ctx: Context) -> IsAvailableResponse {
+    fn check(&self, matched: Vec<&dyn LicenseGeneral>, l: &Limitation, ctx: &Context) -> IsAvailableResponse {
         let count_excl = matched.len();
 
         let mut exclusive_by_set: HashMap<String, i32> = HashMap::new();
@@ -196,7 +216,7 @@ impl LimitCheck for Exclusive {
         }
 
         let mut remain_to_check: Vec<ShrinkedLicenseToken> = Vec::new();
-        for lic in ctx.full.issued_licenses {
+        for lic in ctx.full.issued_licenses.iter() {
             if !exclusives_by_token_id.contains_key(&lic.token_id()) {
                 remain_to_check.push(lic.clone())
             }
@@ -231,8 +251,8 @@ impl LimitCheck for Exclusive {
 }
 
 impl Limitation {
-    pub fn check(&self, licenses: &Vec<&dyn LicenseGeneral>, ctx: Context) -> IsAvailableResponse {
-        let matched_licenses = self.find_all(licenses);
+    pub fn check(&self, licenses: &Vec<&dyn LicenseGeneral>, ctx: &Context, templates: &Templates) -> IsAvailableResponse {
+        let matched_licenses = self.find_all(licenses, templates);
         let checks: Vec<Option<&dyn LimitCheck>> = vec![
             self.max_count.as_ref().map(|x| x as &dyn LimitCheck),
             self.exclusive.as_ref().map(|x| x as &dyn LimitCheck),
@@ -245,7 +265,7 @@ impl Limitation {
             }
             unsafe {
                 let must_check = check.unwrap_unchecked();
-                let res = must_check.check(matched_licenses.clone(), self, ctx.clone());
+                let res = must_check.check(matched_licenses.clone(), self, ctx);
                 if !res.result {
                     return res;
                 }
@@ -258,15 +278,8 @@ impl Limitation {
         return IsAvailableResponse{result: true, reason_not_available: String::new(), additional_info: Some(infos)};
     }
 
-    fn find_all<'a>(&'a self, licenses: &Vec<&'a dyn LicenseGeneral>) -> Vec<&dyn LicenseGeneral> {
-        let mut list: Vec<&dyn LicenseGeneral> = Vec::new();
-        for license in licenses {
-            let result = exec_template(&self.template, *license);
-            if result.is_true() {
-                list.push(*license);
-            }
-        }
-        list
+    fn find_all<'a>(&self, licenses: &[&'a dyn LicenseGeneral], templates: &Templates) -> Vec<&'a dyn LicenseGeneral> {
+        templates.find_all(&self.template, licenses)
     }
 }
 
@@ -277,6 +290,12 @@ impl ConfigInterface for AllPolicies {
         upgrade_rules: Option<Vec<Policy>>) -> Result<IsAvailableResponse, String> {
         // Take into account asset licenses with sets
         // and old/new license_token set_id, compare them etc.
+        let status = old.license_status();
+        if status != LicenseStatus::Active {
+            let msg = format!("License is {}", status);
+            return Ok(IsAvailableResponse { result: false, reason_not_available: msg, additional_info: None });
+        }
+        let inventory = without_terminated(inventory);
         let cloned = self.clone_with_optional(policy_rules, upgrade_rules);
         unsafe {
             // let old_asset_license = inventory.asset.clone().unwrap_or_default().licenses.unwrap_or_default()
@@ -300,8 +319,10 @@ impl ConfigInterface for AllPolicies {
                 return Ok(IsAvailableResponse { result: false, reason_not_available: msg, additional_info: None });
             }
 
-            let policy_old = cloned.find_policy(&old.clone())?;
-            let policy_new = cloned.find_policy(&new.clone())?;
+            let current_ctx = Context{full: inventory.clone()};
+            let templates = Templates::new(Some(&current_ctx));
+            let policy_old = cloned.find_policy(&old.clone(), &templates)?;
+            let policy_new = cloned.find_policy(&new.clone(), &templates)?;
             let exists = policy_old.has_upgrade_to(policy_new.name.as_ref().unwrap_unchecked().clone());
             if !exists {
                 let msg = "No upgrade path to ".to_string() + &policy_new.name.as_ref().unwrap_unchecked().clone();
@@ -326,6 +347,7 @@ impl ConfigInterface for AllPolicies {
         upgrade_rules: Option<Vec<Policy>>) -> IsAvailableResponse {
         // For asset_mint, nft_mint, update_licenses and
         // update inventory licenses (metadata).
+        let inventory = without_terminated(inventory);
         let all_limits = self.get_all_limit_rules_from_asset(
             inventory.asset.as_ref().unwrap(), policy_rules,
         );
@@ -382,7 +404,7 @@ impl ConfigInterface for AllPolicies {
     }
 
     fn check_state(&self, licenses: Vec<ShrinkedLicenseToken>) -> IsAvailableResponse {
-        let ctx = Context{full: FullInventory{issued_licenses: licenses.clone(), inventory_licenses: Vec::new(), asset: None}};
+        let ctx = Context{full: FullInventory{inventory_id: None, issued_licenses: licenses.clone(), inventory_licenses: Vec::new(), asset: None}};
         self.check_future_state(
             licenses.iter().map(|x| x as &dyn LicenseGeneral).collect(),
             FutureStateOpt { level: LEVEL_LICENSES.to_string(), ctx },
@@ -390,7 +412,7 @@ impl ConfigInterface for AllPolicies {
     }
 
     fn check_inventory_state(&self, licenses: Vec<InventoryLicense>) -> IsAvailableResponse {
-        let ctx = Context{full: FullInventory{issued_licenses: Vec::new(), inventory_licenses: licenses.clone(), asset: None}};
+        let ctx = Context{full: FullInventory{inventory_id: None, issued_licenses: Vec::new(), inventory_licenses: licenses.clone(), asset: None}};
         self.check_future_state(
             licenses.iter().map(|x| x as &dyn LicenseGeneral).collect(),
             FutureStateOpt { level: LEVEL_INVENTORY.to_string(), ctx },
@@ -438,6 +460,7 @@ impl ConfigInterface for AllPolicies {
         &self, inventory: FullInventory, policy_rules: Option<Vec<Limitation>>,
         upgrade_rules: Option<Vec<Policy>>) -> Vec<SKUAvailability> {
 
+        let inventory = without_terminated(inventory);
         let all_limits = self.get_all_limit_rules_from_asset(
             inventory.asset.as_ref().unwrap(), policy_rules,
         );
@@ -494,13 +517,39 @@ impl ConfigInterface for AllPolicies {
         available
     }
 
+    fn simulate_asset_change(
+        &self, inventory: FullInventory, new_asset_licenses: Option<Vec<AssetLicense>>,
+        new_policy_rules: Option<Vec<Limitation>>) -> AssetChangeReport {
+        // None keeps the current asset licenses / rules.
+        let asset = inventory.asset.as_ref().expect("Expect asset in inventory");
+        let policy_rules: Option<Vec<Limitation>> = convert_rules(&asset.policy_rules);
+        let upgrade_rules: Option<Vec<Policy>> = convert_rules(&asset.upgrade_rules);
+        let before = self.list_available(inventory.clone(), policy_rules.clone(), upgrade_rules.clone());
+
+        let mut changed = inventory.clone();
+        if new_asset_licenses.is_some() {
+            changed.asset.as_mut().unwrap().licenses = new_asset_licenses;
+        }
+        let new_policy_rules = new_policy_rules.or(policy_rules);
+        let after = self.list_available(changed.clone(), new_policy_rules.clone(), upgrade_rules);
+
+        let all_limits = self.get_all_limit_rules_from_asset(
+            changed.asset.as_ref().unwrap(), new_policy_rules,
+        );
+        let violations = self.clone_with_additional(all_limits).find_violations(&changed);
+
+        AssetChangeReport{
+            violations,
+            availability_changes: diff_availability(before, after),
+        }
+    }
 }
 
 impl AllPolicies {
-    fn find_policy(&self, from: &dyn LicenseGeneral) -> Result<Policy, String> {
+    fn find_policy(&self, from: &dyn LicenseGeneral, templates: &Templates) -> Result<Policy, String> {
         let mut found: String = String::new();
         for (pol_name, pol) in self.policies.iter() {
-            let result = exec_template(&pol.template, from);
+            let result = templates.eval(&pol.template, from);
             if result.is_true() {
                 found = pol_name.clone();
             }
@@ -548,6 +597,34 @@ impl AllPolicies {
         cloned
     }
 
+    pub fn clone_with_inventory(&self, inventory_policies: Option<InventoryPolicies>) -> Self {
+        if let Some(inv) = inventory_policies {
+            return self.clone_with_optional(inv.policy_rules, inv.upgrade_rules)
+        }
+        self.clone()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (pol_name, pol) in self.policies.iter() {
+            if pol.name.is_none() {
+                return Err(format!("Policy {} has no name", pol_name))
+            }
+            check_template(&pol.template)?;
+            for upgrade_to in &pol.upgrade_to {
+                if !self.policies.contains_key(upgrade_to) {
+                    return Err(format!("Policy {} upgrades to unknown policy {}", pol_name, upgrade_to))
+                }
+            }
+        }
+        for l in &self.limitations {
+            check_template(&l.template)?;
+            if l.level != LEVEL_LICENSES && l.level != LEVEL_INVENTORY {
+                return Err(format!("Limitation {} has unknown level {}", l.name, l.level))
+            }
+        }
+        Ok(())
+    }
+
     // fn find_policy_set_id(&self, from: &dyn LicenseGeneral, opt: PolicyOpt) -> Result<Policy, String> {
     //     let mut found: String = String::new();
     //     for (pol_name, pol) in self.policies.iter() {
@@ -604,11 +681,12 @@ impl AllPolicies {
 
     pub fn check_future_state(&self, licenses: Vec<&dyn LicenseGeneral>, opt: FutureStateOpt) -> IsAvailableResponse {
         let mut infos: HashMap<String, LimitsInfo> = HashMap::new();
+        let templates = Templates::new(Some(&opt.ctx));
         for l in &self.limitations {
             if l.level != opt.level {
                 continue;
             }
-            let res = l.check(&licenses, opt.ctx.clone());
+            let res = l.check(&licenses, &opt.ctx, &templates);
             if !res.result {
                 return res;
             }
@@ -621,15 +699,50 @@ impl AllPolicies {
         return IsAvailableResponse{result: true, reason_not_available: String::new(), additional_info: Some(infos)};
     }
 
-    pub fn filter_by_limits(&self, result: IsAvailableResponse, new: &dyn LicenseGeneral) -> IsAvailableResponse {
+    // Checks the issued licenses as they are against every license level limitation
+    // and lists the licenses which break them.
+    pub fn find_violations(&self, inventory: &FullInventory) -> Vec<LicenseViolation> {
+        let inventory = &without_terminated(inventory.clone());
+        let ctx = Context{full: inventory.clone()};
+        let templates = Templates::new(Some(&ctx));
+        let licenses: Vec<&dyn LicenseGeneral> = inventory.issued_licenses.iter().map(|x| x as &dyn LicenseGeneral).collect();
+        let mut violations: Vec<LicenseViolation> = Vec::new();
+        for l in &self.limitations {
+            if l.level != LEVEL_LICENSES {
+                continue;
+            }
+            let res = l.check(&licenses, &ctx, &templates);
+            if res.result {
+                continue;
+            }
+            let matched = l.find_all(&licenses, &templates);
+            // Licenses issued before the max count was reached are still fine
+            let skip = match &l.max_count {
+                Some(max_count) if matched.len() > max_count.count.max(0) as usize => max_count.count.max(0) as usize,
+                _ => 0,
+            };
+            for lic in matched.iter().skip(skip) {
+                violations.push(LicenseViolation{
+                    token_id: lic.token_id(),
+                    sku_id: lic.sku_id(),
+                    limitation: l.name.clone(),
+                    reason: res.reason_not_available.clone(),
+                });
+            }
+        }
+        violations
+    }
+
+    pub fn filter_by_limits(&self, result: IsAvailableResponse, new: &dyn LicenseGeneral, ctx: Option<&Context>) -> IsAvailableResponse {
         if result.additional_info.is_none() {
             return result
         }
         let mut add_info = unsafe {result.additional_info.unwrap_unchecked()};
 
         let new_as_general = vec![new];
+        let templates = Templates::new(ctx);
         for l in &self.limitations {
-            let matched = l.find_all(&new_as_general);
+            let matched = l.find_all(&new_as_general, &templates);
             if matched.len() != 1 {
                 add_info.remove(&l.name);
             }
@@ -650,6 +763,38 @@ impl AllPolicies {
     }
 }
 
+// Revoked and expired licenses don't count against the limits, their objects are free again.
+fn without_terminated(mut inventory: FullInventory) -> FullInventory {
+    inventory.issued_licenses.retain(|x| !x.license_status().is_terminated());
+    inventory
+}
+
+// Asset rules are stored as plain data in the inventory, same shape as the engine types.
+fn convert_rules<F: Serialize, T: for<'de> Deserialize<'de>>(data: &Option<Vec<F>>) -> Option<Vec<T>> {
+    data.as_ref().map(|x| {
+        serde_json::from_value(serde_json::to_value(x).expect("Failed to serialize"))
+            .expect("Failed to parse asset rules")
+    })
+}
+
+fn diff_availability(before: Vec<SKUAvailability>, after: Vec<SKUAvailability>) -> Vec<SKUAvailabilityChange> {
+    let sku_id = |x: &SKUAvailability| x.asset_license.sku_id.clone().unwrap_or_default();
+    let mut changes: Vec<SKUAvailabilityChange> = Vec::new();
+    for b in &before {
+        let a = after.iter().find(|x| sku_id(x) == sku_id(b));
+        let same = a.map(|a| serde_json::to_value(a).ok() == serde_json::to_value(b).ok()).unwrap_or(false);
+        if !same {
+            changes.push(SKUAvailabilityChange{sku_id: sku_id(b), before: Some(b.clone()), after: a.cloned()});
+        }
+    }
+    for a in &after {
+        if !before.iter().any(|x| sku_id(x) == sku_id(a)) {
+            changes.push(SKUAvailabilityChange{sku_id: sku_id(a), before: None, after: Some(a.clone())});
+        }
+    }
+    changes
+}
+
 pub fn max_count_from_sku(sku: &AssetLicense) -> Limitation {
     let limit = Limitation{
         level: LEVEL_LICENSES.to_string(),
@@ -665,27 +810,119 @@ pub fn max_count_from_sku(sku: &AssetLicense) -> Limitation {
     limit
 }
 
-pub fn exec_template(template_str: &String, object: &dyn LicenseGeneral) -> Value {
+pub fn check_template(template_str: &String) -> Result<(), String> {
     let env = minijinja::Environment::new();
-    // env.add_template("tpl", &template_str).expect("Failed to add template");
-
-    unsafe {
-        let expr = env.compile_expression(&template_str).unwrap();
-        // let context = minijinja::context!(
-        //     is_personal => object.is_personal(),
-        //     is_commercial => object.is_commercial(),
-        //     is_exclusive => object.is_exclusive(),
-        // );
-        let mut context = BTreeMap::default();
-        context.insert("is_personal", Value::from_serializable(&object.is_personal()));
-        context.insert("is_commercial", Value::from_serializable(&object.is_commercial()));
-        context.insert("is_exclusive", Value::from_serializable(&object.is_exclusive()));
-        context.insert("sku_id", Value::from_serializable(&object.sku_id()));
-        context.insert("token_id", Value::from_serializable(&object.token_id()));
-        context.insert("license_id", Value::from_serializable(&object.license_id()));
-        context.insert("license_title", Value::from_serializable(&object.license_title()));
-
-        let result = expr.eval(context).unwrap_unchecked();
-        result
+    if env.compile_expression(&template_str).is_err() {
+        return Err(format!("Invalid template: {}", template_str))
     }
-}
\ No newline at end of file
+    Ok(())
+}
+
+pub fn exec_template(template_str: &String, object: &dyn LicenseGeneral, ctx: Option<&Context>) -> Value {
+    Templates::new(ctx).eval(template_str, object)
+}
+
+// Evaluates rule templates. The environment with the template functions and the snapshot of
+// the issued licenses are built once per check and shared by all the evaluated licenses.
+pub struct Templates<'source> {
+    env: minijinja::Environment<'source>,
+}
+
+impl<'source> Templates<'source> {
+    pub fn new(ctx: Option<&Context>) -> Self {
+        let mut env = minijinja::Environment::new();
+        add_template_functions(&mut env, ctx);
+        Templates{env}
+    }
+
+    pub fn eval(&self, template_str: &'source str, object: &dyn LicenseGeneral) -> Value {
+        let expr = self.env.compile_expression(template_str).unwrap();
+        // template functions may fail at runtime, e.g. on a wrong argument type: the template does not match
+        expr.eval(template_context(object)).unwrap_or(Value::from(false))
+    }
+
+    // The licenses matched by the template, compiled once for all of them.
+    pub fn find_all<'a>(&self, template_str: &'source str, licenses: &[&'a dyn LicenseGeneral]) -> Vec<&'a dyn LicenseGeneral> {
+        let expr = self.env.compile_expression(template_str).unwrap();
+        licenses.iter()
+            .filter(|x| expr.eval(template_context(**x)).unwrap_or(Value::from(false)).is_true())
+            .copied()
+            .collect()
+    }
+}
+
+// Variables of the evaluated license, the template functions read the license from them too.
+fn template_context(object: &dyn LicenseGeneral) -> BTreeMap<&'static str, Value> {
+    // let context = minijinja::context!(
+    //     is_personal => object.is_personal(),
+    //     is_commercial => object.is_commercial(),
+    //     is_exclusive => object.is_exclusive(),
+    // );
+    let mut context = BTreeMap::default();
+    context.insert("is_personal", Value::from_serializable(&object.is_personal()));
+    context.insert("is_commercial", Value::from_serializable(&object.is_commercial()));
+    context.insert("is_exclusive", Value::from_serializable(&object.is_exclusive()));
+    context.insert("sku_id", Value::from_serializable(&object.sku_id()));
+    context.insert("token_id", Value::from_serializable(&object.token_id()));
+    context.insert("license_id", Value::from_serializable(&object.license_id()));
+    context.insert("license_title", Value::from_serializable(&object.license_title()));
+    context.insert("owner_id", Value::from_serializable(&object.owner_id()));
+    context.insert("objects", Value::from_serializable(&object.objects()));
+    context.insert("issued_at", Value::from_serializable(&object.issued_at()));
+    context
+}
+
+// Functions available in rule templates:
+//  - has_object(id): the license covers the object
+//  - object_count(): number of objects covered by the license
+//  - days_since_issue(): full days since the license was issued
+//  - issued_count(sku): number of issued licenses of the SKU in the context
+//  - holds_sku(sku): the license owner holds another license of the SKU
+fn add_template_functions(jinja: &mut minijinja::Environment, ctx: Option<&Context>) {
+    jinja.add_function("has_object", |state: &State, id: String| -> Result<bool, Error> {
+        Ok(license_objects(state).contains(&id))
+    });
+    jinja.add_function("object_count", |state: &State| -> Result<u64, Error> {
+        Ok(license_objects(state).len() as u64)
+    });
+
+    jinja.add_function("days_since_issue", |state: &State| -> Result<u64, Error> {
+        let issued_at = state.lookup("issued_at").and_then(|x| u64::try_from(x).ok());
+        let now = env::block_timestamp_ms();
+        Ok(now.saturating_sub(issued_at.unwrap_or(now)) / MS_IN_DAY)
+    });
+
+    // issued licenses counted by SKU, and their token IDs by SKU and owner
+    let mut issued_count: HashMap<String, u64> = HashMap::new();
+    let mut issued_tokens: HashMap<(String, AccountId), Vec<String>> = HashMap::new();
+    for lic in ctx.map(|c| c.full.issued_licenses.as_slice()).unwrap_or_default() {
+        *issued_count.entry(lic.sku_id()).or_default() += 1;
+        if let Some(owner_id) = lic.owner_id() {
+            issued_tokens.entry((lic.sku_id(), owner_id)).or_default().push(lic.token_id());
+        }
+    }
+    jinja.add_function("issued_count", move |_: &State, sku_id: String| -> Result<u64, Error> {
+        Ok(issued_count.get(&sku_id).copied().unwrap_or(0))
+    });
+
+    jinja.add_function("holds_sku", move |state: &State, sku_id: String| -> Result<bool, Error> {
+        let token_id = license_str(state, "token_id").unwrap_or_default();
+        let owner_id = match license_str(state, "owner_id") {
+            Some(owner_id) => AccountId::new_unchecked(owner_id),
+            None => return Ok(false),
+        };
+        Ok(issued_tokens.get(&(sku_id, owner_id)).is_some_and(|tokens| tokens.iter().any(|x| x != &token_id)))
+    });
+}
+
+fn license_str(state: &State, name: &str) -> Option<String> {
+    state.lookup(name).and_then(|x| x.as_str().map(|x| x.to_string()))
+}
+
+fn license_objects(state: &State) -> Vec<String> {
+    let objects = state.lookup("objects").unwrap_or(Value::UNDEFINED);
+    (0..objects.len().unwrap_or(0))
+        .filter_map(|i| objects.get_item(&Value::from(i)).ok())
+        .filter_map(|x| x.as_str().map(|x| x.to_string()))
+        .collect()
+}
@@ -2,11 +2,12 @@
 mod tests {
     use near_sdk::{AccountId, Balance};
     use near_sdk::serde_json;
-    use crate::policy::{init_policies, Limitation, MaxCount};
-    use crate::policy::{ConfigInterface, LEVEL_LICENSES};
-    use common_types::prices::Price;
-    use common_types::utils::{balance_from_string, format_balance, get_inventory_id};
-    use common_types::types::{AssetLicense, FullInventory, InventoryLicense, JsonAssetToken, LicenseData, ObjectData, ObjectItem, TokenMetadata};
+    use crate::policy::{init_policies, exec_template, Context, InventoryPolicies, Limitation, MaxCount, Policy};
+    use crate::policy::{ConfigInterface, LEVEL_INVENTORY, LEVEL_LICENSES};
+    use common_types::prices::{median_price, select_near_price, Asset, FallbackPrice, OracleConfig, Price, Report};
+    use common_types::utils::{amount_from_string, amount_to_string, balance_from_string, format_balance, get_inventory_id, mul_div_round};
+    use common_types::types::{validate_asset_licenses, validate_revenue_split, split_revenue, FilterOpt, LicenseStatus, Payee, SkuTokenData, Transferability};
+    use common_types::types::{AssetLicense, FullInventory, InventoryLicense, JsonAssetToken, LicenseData, ObjectData, ObjectItem, ShrinkedLicenseToken, TokenMetadata};
 
     #[test]
     fn test_init_policies() {
@@ -28,6 +29,7 @@ mod tests {
             exclusivity: Some(exclusive),
             moral_use_restrictions: None,
             version: None,
+            transferability: None,
         }
     }
 
@@ -112,6 +114,7 @@ mod tests {
             minter_id: AccountId::new_unchecked("license_rocketscience".to_string()),
             policy_rules: None,
             upgrade_rules: None,
+            revenue_split: None,
         }
     }
 
@@ -174,6 +177,7 @@ mod tests {
             "token".to_string()
         ).shrink();
         let inventory = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![old_l.clone(), new_l.clone()],
             issued_licenses:    vec![old_token.clone()],
             asset: Some(asset_token),
@@ -269,6 +273,7 @@ mod tests {
             "1".to_string(),
         ).shrink();
         let inventory = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), commercial.clone(), personal_exclusive.clone()],
             issued_licenses:    vec![personal_exclusive_token.clone()],
             asset: Some(asset),
@@ -332,6 +337,7 @@ mod tests {
             Some(personal.clone()), lics[2].clone(), "6".to_string()
         ).shrink();
         let inventory = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), personal_exclusive.clone()],
             issued_licenses:    vec![exclusive_token.clone()],
             asset: Some(asset_token.clone()),
@@ -365,6 +371,7 @@ mod tests {
         assert_eq!(false, available[4].available);
 
         let inventory2 = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), personal_exclusive.clone()],
             issued_licenses:    vec![personal_different_object.clone()],
             asset: Some(asset_token.clone()),
@@ -382,6 +389,71 @@ mod tests {
         assert_eq!(false, available[4].available);
     }
 
+    #[test]
+    fn test_license_status() {
+        assert!(LicenseStatus::Active.check_transition(LicenseStatus::Suspended).is_ok());
+        assert!(LicenseStatus::Suspended.check_transition(LicenseStatus::Active).is_ok());
+        assert!(LicenseStatus::Expired.check_transition(LicenseStatus::Revoked).is_ok());
+        assert_eq!(
+            LicenseStatus::Revoked.check_transition(LicenseStatus::Active).unwrap_err(),
+            "Cannot change license status from revoked to active",
+        );
+        assert!(LicenseStatus::Active.check_transition(LicenseStatus::Expired).is_err());
+
+        let policies = init_policies();
+        let personal_exclusive = InventoryLicense{
+            title: "lic3".to_string(),
+            price: Some("1".to_string()),
+            license_id: "exclusive".to_string(),
+            license: license_data(true, true)
+        };
+        let mut asset_token = sample_asset_token();
+        asset_token.metadata.object = Some(to_string(object_data(vec![object_item("object1")])));
+        asset_token.licenses = Some(vec![asset_license("sku1", "exclusive", &["object1"])]);
+        let lic = asset_token.licenses.clone().unwrap()[0].clone();
+        let issue = |token_id: &str, status: Option<LicenseStatus>, expires_at: Option<u64>| {
+            let mut token = asset_token.issue_new_license(Some(personal_exclusive.clone()), lic.clone(), token_id.to_string());
+            let license = token.license.as_mut().unwrap();
+            license.status = status;
+            license.expires_at = expires_at;
+            token.shrink()
+        };
+        let inventory = |issued: ShrinkedLicenseToken| FullInventory{
+            inventory_id: None,
+            inventory_licenses: vec![personal_exclusive.clone()],
+            issued_licenses: vec![issued],
+            asset: Some(asset_token.clone()),
+        };
+
+        // Suspended license still holds the exclusive object
+        let suspended = issue("1", Some(LicenseStatus::Suspended), None);
+        assert_eq!(suspended.license_status(), LicenseStatus::Suspended);
+        let res = policies.check_new(inventory(suspended.clone()), issue("2", None, None), None, None);
+        assert!(!res.result);
+        let res = policies.check_transition(inventory(suspended.clone()), suspended, issue("1", None, None), None, None).unwrap();
+        assert_eq!(res.reason_not_available, "License is suspended");
+
+        // Revoked and expired ones free it
+        let revoked = issue("1", Some(LicenseStatus::Revoked), None);
+        let res = policies.check_new(inventory(revoked.clone()), issue("2", None, None), None, None);
+        assert!(res.result);
+        assert!(policies.list_available(inventory(revoked), None, None)[0].available);
+
+        let mut license = asset_token.issue_new_license(Some(personal_exclusive.clone()), lic.clone(), "1".to_string()).license.unwrap();
+        license.starts_at = Some(100);
+        license.expires_at = Some(200);
+        assert!(!license.is_valid_at(99));
+        assert!(license.is_valid_at(100));
+        assert!(!license.is_valid_at(200));
+        license.status = Some(LicenseStatus::Suspended);
+        assert!(!license.is_valid_at(150));
+
+        let expired = issue("1", None, Some(0));
+        assert_eq!(expired.license_status(), LicenseStatus::Expired);
+        let res = policies.check_new(inventory(expired), issue("2", None, None), None, None);
+        assert!(res.result);
+    }
+
     #[test]
     fn test_check_inventory_state() {
         let policies = init_policies();
@@ -406,6 +478,7 @@ mod tests {
         };
         let personal_exclusive_token = personal_exclusive.as_license_token("1".to_string()).shrink();
         let inventory = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), commercial.clone(), personal_exclusive.clone()],
             issued_licenses:    vec![personal_exclusive_token.clone()],
             asset: None,
@@ -417,6 +490,7 @@ mod tests {
         assert_eq!(res.result, true);
 
         let inventory2 = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), commercial.clone(), personal_exclusive.clone(), personal_exclusive.clone()],
             issued_licenses:    vec![personal_exclusive_token.clone()],
             asset: None,
@@ -429,6 +503,106 @@ mod tests {
         assert_eq!(res2.reason_not_available.contains("max count 1"), true)
     }
 
+    #[test]
+    fn test_inventory_policies() {
+        let policies = init_policies();
+
+        let commercial = InventoryLicense{
+            title: "lic2".to_string(),
+            price: Some("1".to_string()),
+            license_id: "commercial".to_string(),
+            license: license_data(false, false)
+        };
+        let commercial2 = InventoryLicense{
+            title: "lic3".to_string(),
+            price: Some("1".to_string()),
+            license_id: "commercial2".to_string(),
+            license: license_data(false, false)
+        };
+        let inventory_policies = InventoryPolicies{
+            policy_rules: Some(vec![Limitation{
+                name: "one commercial".to_string(),
+                display_name: None,
+                scope: "general".to_string(),
+                level: LEVEL_INVENTORY.to_string(),
+                template: "is_commercial".to_string(),
+                max_count: Some(MaxCount{count: 1}),
+                exclusive: None,
+            }]),
+            upgrade_rules: Some(vec![Policy{
+                name: Some("personal".to_string()),
+                template: "is_personal and not is_exclusive".to_string(),
+                upgrade_to: vec!["commercial".to_string()],
+                user_defined: Some(true),
+            }]),
+        };
+
+        let resolved = policies.clone_with_inventory(Some(inventory_policies.clone()));
+        assert_eq!(resolved.validate().is_ok(), true);
+        assert_eq!(resolved.policies.get("personal").unwrap().upgrade_to, vec!["commercial".to_string()]);
+        assert_eq!(policies.policies.get("personal").unwrap().upgrade_to.len(), 3);
+
+        let res = policies.check_inventory_state(vec![commercial.clone(), commercial2.clone()]);
+        assert_eq!(res.result, true);
+        let res = resolved.check_inventory_state(vec![commercial.clone(), commercial2.clone()]);
+        assert_eq!(res.result, false);
+        assert_eq!(res.reason_not_available.contains("max count 1"), true);
+
+        let mut broken = inventory_policies.clone();
+        broken.upgrade_rules.as_mut().unwrap()[0].upgrade_to = vec!["unknown".to_string()];
+        let res = policies.clone_with_inventory(Some(broken)).validate();
+        assert_eq!(res.is_err(), true);
+    }
+
+    #[test]
+    fn test_template_functions() {
+        let personal = InventoryLicense{
+            title: "lic1".to_string(),
+            price: Some("1".to_string()),
+            license_id: "personal".to_string(),
+            license: license_data(true, false)
+        };
+        let mut asset_token = sample_asset_token();
+        asset_token.metadata.object = Some(to_string(object_data(vec![
+            object_item("object1"),
+            object_item("object2"),
+        ])));
+        asset_token.licenses = Some(vec![
+            asset_license("sku1", "personal", &["object1"]),
+            asset_license("sku2", "personal", &["object1", "object2"]),
+        ]);
+        let lics = asset_token.licenses.clone().unwrap();
+        let mut first = asset_token.issue_new_license(Some(personal.clone()), lics[0].clone(), "1".to_string());
+        first.owner_id = AccountId::new_unchecked("bob".to_string());
+        let mut second = asset_token.issue_new_license(Some(personal.clone()), lics[1].clone(), "2".to_string());
+        second.owner_id = AccountId::new_unchecked("bob".to_string());
+        let mut other = asset_token.issue_new_license(Some(personal.clone()), lics[1].clone(), "3".to_string());
+        other.owner_id = AccountId::new_unchecked("alice".to_string());
+
+        let ctx = Context{full: FullInventory{
+            inventory_id: None,
+            inventory_licenses: vec![personal.clone()],
+            issued_licenses: vec![first.shrink(), second.shrink(), other.shrink()],
+            asset: Some(asset_token),
+        }};
+        let second = second.shrink();
+        let other = other.shrink();
+
+        let eval = |tpl: &str, lic: &ShrinkedLicenseToken| exec_template(&tpl.to_string(), lic, Some(&ctx)).is_true();
+        assert_eq!(eval("has_object('object2')", &second), true);
+        assert_eq!(eval("has_object('object3')", &second), false);
+        assert_eq!(eval("object_count() == 2", &second), true);
+        assert_eq!(eval("issued_count('sku2') == 2", &second), true);
+        assert_eq!(eval("holds_sku('sku1')", &second), true);
+        assert_eq!(eval("holds_sku('sku1')", &other), false);
+        assert_eq!(eval("holds_sku('sku2')", &other), false);
+        assert_eq!(eval("days_since_issue() == 0", &other), true);
+        assert_eq!(exec_template(&"issued_count('sku2') == 0".to_string(), &other, None).is_true(), true);
+        // failing functions do not match
+        assert_eq!(eval("has_object()", &second), false);
+        assert_eq!(eval("not has_object()", &second), false);
+    }
+
     #[test]
     fn test_check_new_limitation_count() {
         let policies = init_policies();
@@ -471,6 +645,7 @@ mod tests {
             Some(personal.clone()), lics[0].clone(), "3".to_string()
         ).shrink();
         let inventory = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), commercial.clone(), personal_exclusive.clone()],
             issued_licenses:    vec![personal_token.clone(), personal_token2.clone(), personal_token3.clone()],
             asset: Some(asset_token.clone()),
@@ -494,6 +669,7 @@ mod tests {
 
 
         let inventory2 = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), commercial.clone(), personal_exclusive.clone()],
             issued_licenses:    vec![personal_token.clone(), personal_token2.clone()],
             asset: Some(asset_token.clone()),
@@ -561,6 +737,7 @@ mod tests {
         ).shrink();
 
         let inventory = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), commercial.clone()],
             issued_licenses:    vec![personal_token.clone()],
             asset: Some(asset_token.clone()),
@@ -575,6 +752,7 @@ mod tests {
         assert_eq!(limit_info.remains == 0, true);
 
         let inventory2 = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), commercial.clone()],
             issued_licenses:    vec![personal_token.clone(), personal_token2.clone()],
             asset: Some(asset_token.clone()),
@@ -596,6 +774,7 @@ mod tests {
         assert_eq!(limit_sku2.issued == 0, true);
 
         let inventory3 = FullInventory{
+            inventory_id: None,
             inventory_licenses: vec![personal.clone(), commercial.clone()],
             issued_licenses:    vec![personal_token.clone(), commercial_token.clone()],
             asset: Some(asset_token.clone()),
@@ -618,6 +797,78 @@ mod tests {
         assert_eq!(limit_sku2.issued == 1, true);
     }
 
+    #[test]
+    fn test_simulate_asset_change() {
+        let policies = init_policies();
+
+        let personal = InventoryLicense{
+            title: "lic1".to_string(),
+            price: Some("1".to_string()),
+            license_id: "personal".to_string(),
+            license: license_data(true, false)
+        };
+        let mut asset_token = sample_asset_token();
+        asset_token.metadata.object = Some(to_string(object_data(vec![
+            object_item("object1"),
+        ])));
+        asset_token.licenses = Some(vec![
+            asset_license("sku1", "personal", &["object1"]),
+            asset_license("sku2", "personal", &["object1"]),
+        ]);
+        asset_token.licenses.as_mut().unwrap()[0].sole_limit = Some(3);
+
+        let lics = asset_token.licenses.clone().unwrap();
+        let issued: Vec<ShrinkedLicenseToken> = (1..=3).map(|i| asset_token.issue_new_license(
+            Some(personal.clone()), lics[0].clone(), i.to_string()
+        ).shrink()).collect();
+
+        let inventory = FullInventory{
+            inventory_id: None,
+            inventory_licenses: vec![personal.clone()],
+            issued_licenses:    issued,
+            asset: Some(asset_token.clone()),
+        };
+
+        // Nothing changes
+        let res = policies.simulate_asset_change(inventory.clone(), None, None);
+        assert_eq!(res.violations.len(), 0);
+        assert_eq!(res.availability_changes.len(), 0);
+
+        // Lower sku1 limit below the sold count
+        let mut new_lics = lics.clone();
+        new_lics[0].sole_limit = Some(1);
+        let res = policies.simulate_asset_change(inventory.clone(), Some(new_lics), None);
+        let token_ids: Vec<String> = res.violations.iter().map(|x| x.token_id.clone()).collect();
+        assert_eq!(token_ids, vec!["2".to_string(), "3".to_string()]);
+        assert_eq!(res.violations[0].limitation, "sku1");
+        assert!(res.violations[0].reason.contains("Cannot set more sku1: max count 1"));
+        // Broken sku1 limit blocks the other SKUs as well
+        assert_eq!(res.availability_changes.len(), 2);
+        let sku2 = res.availability_changes.iter().find(|x| x.sku_id == "sku2").unwrap();
+        assert!(sku2.before.as_ref().unwrap().available);
+        assert!(!sku2.after.as_ref().unwrap().available);
+
+        // New limitation on the whole asset, sku2 is removed
+        let limit = Limitation{
+            name: "asset_limit".to_string(),
+            display_name: None,
+            scope: "general".to_string(),
+            level: LEVEL_LICENSES.to_string(),
+            template: "true".to_string(),
+            max_count: Some(MaxCount{count: 2}),
+            exclusive: None,
+        };
+        let res = policies.simulate_asset_change(
+            inventory, Some(vec![lics[0].clone()]), Some(vec![limit]),
+        );
+        assert_eq!(res.violations.len(), 1);
+        assert_eq!(res.violations[0].token_id, "3");
+        assert_eq!(res.violations[0].limitation, "asset_limit");
+        let removed = res.availability_changes.iter().find(|x| x.sku_id == "sku2").unwrap();
+        assert!(removed.before.as_ref().unwrap().available);
+        assert!(removed.after.is_none());
+    }
+
     #[test]
     fn test_balance_from_string() {
         let price1 = "0.3".to_string();
@@ -644,6 +895,165 @@ mod tests {
         assert_eq!(format_balance(balance5), price5);
     }
 
+    #[test]
+    fn test_sku_slippage() {
+        let mut asset = sample_asset_token();
+        asset.metadata.object = Some(String::new());
+        let inventory_licenses = vec![InventoryLicense{
+            title: "lic1".to_string(),
+            price: Some("1".to_string()),
+            license_id: "id1".to_string(),
+            license: license_data(false, true),
+        }];
+        let mut licenses = vec![asset_license("set1", "id1", &[])];
+        licenses[0].params = Some(r#"{"slippage_percents": 10}"#.to_string());
+        assert_eq!(licenses[0].get_params().slippage_percents, Some(10));
+        assert!(validate_asset_licenses(&licenses, &inventory_licenses, &asset.metadata).is_ok());
+
+        licenses[0].params = Some(r#"{"slippage_percents": 101}"#.to_string());
+        assert_eq!(
+            validate_asset_licenses(&licenses, &inventory_licenses, &asset.metadata),
+            Err(vec!["SKU set1: slippage_percents 101 is greater than 100".to_string()]),
+        );
+    }
+
+    #[test]
+    fn test_revenue_split() {
+        let payee = |account_id: &str, bps: u32| Payee{account_id: AccountId::new_unchecked(account_id.to_string()), bps};
+        let payees = vec![payee("alice", 5000), payee("bob", 3333), payee("carol", 1667)];
+        assert!(validate_revenue_split(&payees).is_ok());
+        assert_eq!(validate_revenue_split(&[]), Err("Revenue split has no payees".to_string()));
+        assert_eq!(
+            validate_revenue_split(&[payee("alice", 5000), payee("bob", 4000)]),
+            Err("Revenue split sums up to 9000 bps instead of 10000".to_string()),
+        );
+        assert_eq!(
+            validate_revenue_split(&[payee("alice", 5000), payee("alice", 5000)]),
+            Err("Payee alice: duplicate account".to_string()),
+        );
+        assert_eq!(
+            validate_revenue_split(&[payee("alice", 10000), payee("bob", 0)]),
+            Err("Payee bob: zero share".to_string()),
+        );
+        let many: Vec<Payee> = (0..11).map(|i| payee(&format!("payee{}", i), if i == 0 { 9000 } else { 100 })).collect();
+        assert_eq!(
+            validate_revenue_split(&many),
+            Err("Revenue split has 11 payees, at most 10 allowed".to_string()),
+        );
+        let ten: Vec<Payee> = (0..10).map(|i| payee(&format!("payee{}", i), 1000)).collect();
+        assert!(validate_revenue_split(&ten).is_ok());
+
+        // the rounding remainder goes to the last payee
+        let shares: Vec<Balance> = split_revenue(1001, &payees).into_iter().map(|x| x.1).collect();
+        assert_eq!(shares, vec![500, 333, 168]);
+        assert_eq!(split_revenue(0, &payees).into_iter().map(|x| x.1).sum::<Balance>(), 0);
+
+        // asset split, then inventory split, then the fallback account
+        let mut asset = sample_asset_token();
+        let owner = AccountId::new_unchecked("owner".to_string());
+        assert_eq!(asset.revenue_payees(None, owner.clone()), vec![payee("owner", 10000)]);
+        assert_eq!(asset.revenue_payees(Some(&payees), owner.clone()), payees);
+        asset.revenue_split = Some(vec![payee("dave", 10000)]);
+        assert_eq!(asset.revenue_payees(Some(&payees), owner), vec![payee("dave", 10000)]);
+    }
+
+    #[test]
+    fn test_refund_window() {
+        let inventory_licenses = vec![InventoryLicense {
+            title: "lic1".to_string(),
+            price: Some("1".to_string()),
+            license_id: "id1".to_string(),
+            license: license_data(false, true),
+        }];
+        let asset = sample_asset_token();
+        let mut licenses = vec![asset_license("set1", "id1", &[])];
+        licenses[0].params = Some(r#"{"refund_window_days": 0}"#.to_string());
+        assert_eq!(
+            validate_asset_licenses(&licenses, &inventory_licenses, &asset.metadata),
+            Err(vec!["SKU set1: zero refund_window_days".to_string()]),
+        );
+
+        let mut metadata = TokenMetadata{issued_at: Some(1_000), ..Default::default()};
+        assert_eq!(metadata.refund_deadline(), None);
+        metadata.sku_data = Some(SkuTokenData{title: "set1".to_string(), params: None});
+        assert_eq!(metadata.refund_deadline(), None);
+        metadata.sku_data = Some(SkuTokenData{title: "set1".to_string(), params: Some(r#"{"refund_window_days": 14}"#.to_string())});
+        assert_eq!(metadata.refund_deadline(), Some(1_000 + 14 * 24 * 3600 * 1000));
+    }
+
+    #[test]
+    fn test_exact_balance() {
+        let one_near: Balance = 1_000_000_000_000_000_000_000_000;
+        let cases = [
+            ("0", 0),
+            ("0.000000000000000000000001", 1),
+            ("0.000000000000000000000009", 9),
+            ("0.1", one_near / 10),
+            ("1", one_near),
+            ("123456789.123456789123456789123456", 123456789_123456789123456789123456),
+            ("340282366920938.463463374607431768211455", u128::MAX),
+        ];
+        for (string, balance) in cases {
+            assert_eq!(balance_from_string(string.to_string()), balance, "{}", string);
+            assert_eq!(format_balance(balance), string, "{}", string);
+        }
+        // digits after the 24th decimal are dropped, trailing zeros are trimmed
+        assert_eq!(balance_from_string("0.0000000000000000000000019".to_string()), 1);
+        assert_eq!(balance_from_string("1.500".to_string()), one_near * 3 / 2);
+        assert_eq!(format_balance(one_near * 3 / 2), "1.5");
+        assert!(amount_from_string("340282366920938.463463374607431768211456", 24).is_err());
+
+        assert_eq!(amount_to_string(1_500_000, 6), "1.5");
+        assert_eq!(amount_to_string(1, 6), "0.000001");
+        assert_eq!(amount_to_string(10, 0), "10");
+        assert_eq!(amount_to_string(0, 0), "0");
+
+        assert_eq!(mul_div_round(10, 1, 4), Some(3));
+        assert_eq!(mul_div_round(9, 1, 4), Some(2));
+        assert_eq!(mul_div_round(2, 1, 4), Some(1));
+        assert_eq!(mul_div_round(1, 1, 4), Some(0));
+        assert_eq!(mul_div_round(u128::MAX, 3, 3), Some(u128::MAX));
+        assert_eq!(mul_div_round(u128::MAX, 3, 2), None);
+        assert_eq!(mul_div_round(1, 1, 0), None);
+        let one_near = 10u128.pow(24);
+        assert_eq!(mul_div_round(10 * one_near, 3 * one_near, 4 * one_near), Some(7_500_000 * one_near / 1_000_000));
+        assert_eq!(mul_div_round(7 * one_near + 1, 5 * one_near, 10 * one_near), Some(3_500_000_000_000_000_000_000_001));
+        assert_eq!(mul_div_round(u128::MAX - 1, u128::MAX - 1, u128::MAX), Some(u128::MAX - 2));
+    }
+
+    #[test]
+    fn test_near_cost_rounding() {
+        let near_cost = |price: &str, multiplier: &str, decimals: u8| {
+            let mut al = asset_license("sku", "license", &["title"]);
+            al.currency = Some("USD".to_string());
+            al.price = price.to_string();
+            al.get_near_cost(&Price{multiplier: multiplier.to_string(), decimals})
+        };
+        // 1 USD per NEAR
+        assert_eq!(near_cost("1", "10000", 28), "1");
+        assert_eq!(near_cost("0.000001", "10000", 28), "0.000001");
+        assert_eq!(near_cost("0.0000004", "10000", 28), "0");
+        assert_eq!(near_cost("0.0000005", "10000", 28), "0.000001");
+        assert_eq!(near_cost("123456789.1234564999", "1", 24), "123456789.123456");
+        assert_eq!(near_cost("123456789.1234565", "1", 24), "123456789.123457");
+        // 3 USD per NEAR
+        assert_eq!(near_cost("1", "3", 24), "0.333333");
+        assert_eq!(near_cost("2", "3", 24), "0.666667");
+        assert_eq!(near_cost("3", "3", 24), "1");
+        // 8 USD per NEAR, exactly half a micro NEAR rounds up
+        assert_eq!(near_cost("0.000004", "8", 24), "0.000001");
+        assert_eq!(near_cost("0.000003", "8", 24), "0");
+        // prices with less than 24 decimals
+        assert_eq!(near_cost("10", "5", 23), "0.2");
+        // large SKU prices keep every digit
+        assert_eq!(near_cost("1000000", "13542", 28), "738443.361394");
+        assert_eq!(near_cost("99999999999", "13542", 28), "73844336138.679663");
+
+        let mut al = asset_license("sku", "license", &["title"]);
+        al.price = "12.3456789".to_string();
+        assert_eq!(al.get_near_cost(&Price{multiplier: "1".to_string(), decimals: 24}), "12.3456789");
+    }
+
     #[test]
     fn test_price_string() {
         let price_near = Price{
@@ -673,6 +1083,155 @@ mod tests {
         assert_eq!(inv_id2, "i45_awesome.rocketscience.testnet".to_string());
     }
 
+    #[test]
+    fn test_validate_asset_licenses() {
+        let personal = InventoryLicense{
+            title: "lic1".to_string(),
+            price: Some("1".to_string()),
+            license_id: "personal".to_string(),
+            license: license_data(true, false)
+        };
+        let mut metadata = sample_asset_token().metadata;
+        metadata.object = Some(to_string(object_data(vec![
+            object_item("object1"),
+            object_item("object2"),
+        ])));
+
+        let licenses = vec![
+            asset_license("sku1", "personal", &["object1"]),
+            asset_license("sku2", "personal", &["object1", "object2"]),
+        ];
+        assert!(validate_asset_licenses(&licenses, &vec![personal.clone()], &metadata).is_ok());
+
+        let mut licenses = vec![
+            asset_license("sku1", "personal", &["object1"]),
+            asset_license("sku1", "personal", &["object1"]),
+            asset_license("sku2", "unknown", &["object3"]),
+            asset_license("sku3", "personal", &[]),
+        ];
+        licenses[2].sole_limit = Some(-1);
+        licenses[3].price = "1,5".to_string();
+        licenses[3].objects = None;
+        licenses[3].params = Some(r#"{"royalty": 10001, "transferability": {"allowlisted": {"account_ids": []}}}"#.to_string());
+        let errors = validate_asset_licenses(&licenses, &vec![personal.clone()], &metadata).unwrap_err();
+        assert_eq!(errors, vec![
+            "SKU sku1: duplicate sku_id".to_string(),
+            "SKU sku2: license_id unknown not found in inventory licenses".to_string(),
+            "SKU sku2: object object3 not found in asset objects".to_string(),
+            "SKU sku2: negative sole_limit -1".to_string(),
+            "SKU sku3: objects are not set".to_string(),
+            "SKU sku3: invalid price 1,5".to_string(),
+            "SKU sku3: royalty 10001 is greater than 100%".to_string(),
+            "SKU sku3: transfer allowlist is empty".to_string(),
+        ]);
+
+        // Objects are not checked for an asset without objects
+        metadata.object = None;
+        let licenses = vec![asset_license("sku1", "personal", &["object3"])];
+        assert!(validate_asset_licenses(&licenses, &vec![personal], &metadata).is_ok());
+    }
+
+    #[test]
+    fn test_transferability() {
+        let alice = AccountId::new_unchecked("alice".to_string());
+        let bob = AccountId::new_unchecked("bob".to_string());
+        let mut license = license_data(true, false);
+        license.transferability = Some(Transferability::NonTransferable);
+        let personal = InventoryLicense{
+            title: "lic1".to_string(),
+            price: Some("1".to_string()),
+            license_id: "personal".to_string(),
+            license,
+        };
+        let mut asset = sample_asset_token();
+        asset.metadata.object = Some(String::new());
+
+        // Inventory license rule is used by default
+        let token = asset.issue_new_license(Some(personal.clone()), asset_license("set1", "personal", &[]), "1".to_string());
+        let rule = token.license.unwrap().transferability();
+        assert_eq!(rule, Transferability::NonTransferable);
+        assert_eq!(rule.check_receiver(&bob).unwrap_err(), "License token is non-transferable");
+
+        // SKU params override it
+        let mut sku = asset_license("set1", "personal", &[]);
+        sku.params = Some(r#"{"transferability": {"allowlisted": {"account_ids": ["alice"]}}}"#.to_string());
+        let token = asset.issue_new_license(Some(personal.clone()), sku, "1".to_string());
+        let rule = token.license.unwrap().transferability();
+        assert!(rule.check_receiver(&alice).is_ok());
+        assert!(rule.check_receiver(&bob).is_err());
+        assert_eq!(rule.fee(), None);
+
+        let mut sku = asset_license("set1", "personal", &[]);
+        sku.params = Some(r#"{"transferability": {"with_fee": {"fee": "100"}}}"#.to_string());
+        let token = asset.issue_new_license(Some(personal), sku, "1".to_string());
+        let rule = token.license.unwrap().transferability();
+        assert!(rule.check_receiver(&bob).is_ok());
+        assert_eq!(rule.fee(), Some((None, 100)));
+
+        assert_eq!(Transferability::default().fee(), None);
+        assert!(Transferability::default().check_receiver(&bob).is_ok());
+    }
+
+    #[test]
+    fn test_sku_duration() {
+        let personal = InventoryLicense{
+            title: "lic1".to_string(),
+            price: Some("1".to_string()),
+            license_id: "personal".to_string(),
+            license: license_data(true, false),
+        };
+        let mut asset = sample_asset_token();
+        asset.metadata.object = Some(String::new());
+
+        let token = asset.issue_new_license(Some(personal.clone()), asset_license("set1", "personal", &[]), "1".to_string());
+        assert_eq!(token.metadata.expires_at, None);
+        assert_eq!(token.license.unwrap().expires_at, None);
+
+        let mut sku = asset_license("set1", "personal", &[]);
+        sku.params = Some(r#"{"duration_days": 365}"#.to_string());
+        assert_eq!(sku.expires_at(1000), Some(1000 + 365 * 24 * 60 * 60 * 1000));
+        let token = asset.issue_new_license(Some(personal.clone()), sku.clone(), "1".to_string());
+        let expires_at = token.license.as_ref().unwrap().expires_at;
+        assert_eq!(expires_at, sku.expires_at(token.metadata.issued_at.unwrap()));
+        assert_eq!(token.metadata.expires_at, expires_at);
+
+        sku.params = Some(r#"{"duration_days": 0}"#.to_string());
+        let errors = validate_asset_licenses(&[sku], &[personal], &asset.metadata).unwrap_err();
+        assert_eq!(errors, vec!["SKU set1: zero duration_days".to_string()]);
+    }
+
+    #[test]
+    fn test_filter_opt() {
+        let exclusive = InventoryLicense{
+            title: "lic1".to_string(),
+            price: Some("1".to_string()),
+            license_id: "exclusive".to_string(),
+            license: license_data(false, true),
+        };
+        let mut asset = sample_asset_token();
+        asset.metadata.object = Some(String::new());
+        let mut token = asset.issue_new_license(Some(exclusive), asset_license("set1", "exclusive", &[]), "1".to_string());
+        token.license.as_mut().unwrap().starts_at = Some(100);
+
+        let filter = |f: fn(&mut FilterOpt)| {
+            let mut filter = FilterOpt::default();
+            f(&mut filter);
+            filter
+        };
+        assert!(FilterOpt::default().matches(&token, 100));
+        assert!(filter(|x| x.account_id = Some(AccountId::new_unchecked("alice".to_string()))).matches(&token, 100));
+        assert!(!filter(|x| x.account_id = Some(AccountId::new_unchecked("bob".to_string()))).matches(&token, 100));
+        assert!(filter(|x| x.asset_id = Some("asset_normal".to_string())).matches(&token, 100));
+        assert!(filter(|x| x.sku_id = Some("set1".to_string())).matches(&token, 100));
+        assert!(!filter(|x| x.sku_id = Some("set2".to_string())).matches(&token, 100));
+        assert!(filter(|x| x.license_id = Some("exclusive".to_string())).matches(&token, 100));
+        assert!(!filter(|x| x.license_id = Some("personal".to_string())).matches(&token, 100));
+        assert!(filter(|x| x.exclusive = Some(true)).matches(&token, 100));
+        assert!(!filter(|x| x.exclusive = Some(false)).matches(&token, 100));
+        assert!(filter(|x| x.valid = Some(true)).matches(&token, 100));
+        assert!(filter(|x| x.valid = Some(false)).matches(&token, 99));
+    }
+
     #[test]
     fn test_get_near_cost() {
         let near_price = Price{multiplier: "13542".to_string(), decimals: 28};
@@ -698,6 +1257,74 @@ mod tests {
         assert_eq!(cost4, "7.384434".to_string());
     }
 
+    #[test]
+    fn test_select_near_price() {
+        let price = |multiplier: &str, decimals: u8| Price{multiplier: multiplier.to_string(), decimals};
+        let report = |multiplier: &str, timestamp_ms: u64| Report{
+            oracle_id: AccountId::new_unchecked("oracle".to_string()),
+            timestamp: (timestamp_ms * 1_000_000).to_string(),
+            price: price(multiplier, 28),
+        };
+        let string_price = |p: Result<Price, String>| p.map(|x| x.string_price());
+
+        let median = median_price(&[price("3", 28), price("1", 28), price("20", 29)]).unwrap();
+        assert_eq!((median.multiplier.as_str(), median.decimals), ("20", 29));
+        let median = median_price(&[price("1", 28), price("2", 28)]).unwrap();
+        assert_eq!((median.multiplier.as_str(), median.decimals), ("15", 29));
+        assert!(median_price(&[]).is_none());
+
+        let asset = Asset{reports: vec![
+            report("10000", 1_000_000),
+            report("30000", 1_080_000),
+            report("20000", 1_090_000),
+            report("90000", 900_000),
+        ]};
+        let now = 1_100_000;
+        assert_eq!(string_price(select_near_price(Some(&asset), now, 120, None)), Ok("2".to_string()));
+        assert_eq!(string_price(select_near_price(Some(&asset), now, 90, None)), Ok("2.5".to_string()));
+        assert_eq!(string_price(select_near_price(Some(&asset), now, 15, None)), Ok("2".to_string()));
+        assert_eq!(select_near_price(Some(&asset), now, 5, None).err(),
+                   Some("NEAR price reports are older than 5 seconds".to_string()));
+
+        let fallback = FallbackPrice{price: price("50000", 28), expires_at: now + 1};
+        assert_eq!(string_price(select_near_price(Some(&asset), now, 5, Some(&fallback))), Ok("5".to_string()));
+        assert_eq!(string_price(select_near_price(None, now, 5, Some(&fallback))), Ok("5".to_string()));
+        assert_eq!(string_price(select_near_price(Some(&asset), now, 90, Some(&fallback))), Ok("2.5".to_string()));
+        let expired = FallbackPrice{price: price("50000", 28), expires_at: now};
+        assert_eq!(select_near_price(None, now, 5, Some(&expired)).err(),
+                   Some("Failed call priceoracle.get_asset".to_string()));
+
+        assert!(fallback.validate().is_ok());
+        assert!(FallbackPrice{price: price("abc", 28), expires_at: now}.validate().is_err());
+        assert!(FallbackPrice{price: price("0", 28), expires_at: now}.validate().is_err());
+        assert!(FallbackPrice{price: price(&u128::MAX.to_string(), 0), expires_at: now}.validate().is_err());
+        assert!(OracleConfig{account_id: None, max_age_sec: None}.validate().is_ok());
+        assert!(OracleConfig{account_id: None, max_age_sec: Some(60)}.validate().is_ok());
+        assert!(OracleConfig{account_id: None, max_age_sec: Some(0)}.validate().is_err());
+    }
+
+    #[test]
+    fn test_get_ft_cost() {
+        assert_eq!(amount_from_string("1.5", 6), Ok(1_500_000));
+        assert_eq!(amount_from_string("0.000001", 6), Ok(1));
+        assert_eq!(amount_from_string("10", 6), Ok(10_000_000));
+        assert_eq!(amount_from_string(".5", 2), Ok(50));
+        assert_eq!(amount_from_string("2.50", 1), Ok(25));
+        assert_eq!(amount_from_string("0", 24), Ok(0));
+        assert_eq!(amount_from_string("1", 24), Ok(1_000_000_000_000_000_000_000_000));
+        assert!(amount_from_string("0.0000001", 6).is_err());
+        assert!(amount_from_string("1e6", 6).is_err());
+        assert!(amount_from_string("-1", 6).is_err());
+        assert!(amount_from_string("", 6).is_err());
+
+        let mut al = asset_license("sku", "license", &["title"]);
+        al.price = "19.99".to_string();
+        assert!(al.get_ft_cost("USD", 6).is_err());
+        al.currency = Some("USD".to_string());
+        assert_eq!(al.get_ft_cost("USD", 6), Ok(19_990_000));
+        assert!(al.get_ft_cost("EUR", 6).is_err());
+    }
+
     #[test]
     fn test_migrate_to_sku() {
         let json_asset = JsonAssetToken{
@@ -760,6 +1387,7 @@ mod tests {
             minter_id: AccountId::new_unchecked("license_rocketscience".to_string()),
             policy_rules: None,
             upgrade_rules: None,
+            revenue_split: None,
         };
 
         assert_eq!(json_asset.licenses.clone().unwrap()[0].sku_id.clone().unwrap(), "sku1");
@@ -833,6 +1461,7 @@ mod tests {
             minter_id: AccountId::new_unchecked("license_rocketscience".to_string()),
             policy_rules: None,
             upgrade_rules: None,
+            revenue_split: None,
         };
 
         let licenses = json_asset.licenses.clone().unwrap();
@@ -11,4 +11,29 @@ pub struct SKUAvailability {
     pub upgrade_price: Option<String>,
     pub reason_not_available: Option<String>,
     pub additional_info: Option<HashMap<String, LimitsInfo>>,
-}
\ No newline at end of file
+}
+// Issued license which does not fit a changed asset configuration.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LicenseViolation {
+    pub token_id: String,
+    pub sku_id: String,
+    pub limitation: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SKUAvailabilityChange {
+    pub sku_id: String,
+    pub before: Option<SKUAvailability>,
+    pub after: Option<SKUAvailability>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AssetChangeReport {
+    pub violations: Vec<LicenseViolation>,
+    // Only SKUs whose availability differs
+    pub availability_changes: Vec<SKUAvailabilityChange>,
+}
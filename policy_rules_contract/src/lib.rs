@@ -1,9 +1,17 @@
 use near_sdk::{AccountId, env, near_bindgen, PanicOnDefault};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
 use near_sdk::json_types::{Base64VecU8};
-use policy_rules::policy::{AllPolicies, init_policies};
+use policy_rules::policy::{AllPolicies, InventoryPolicies, init_policies};
 
 pub mod policy_contract;
+pub mod registry;
+
+/// Helper structure for keys of the persistent collections.
+#[derive(BorshSerialize)]
+pub enum StorageKey {
+    InventoryPolicies,
+}
 
 
 #[near_bindgen]
@@ -12,6 +20,8 @@ struct Contract {
     // contract owner
     pub owner_id: AccountId,
     pub policies: AllPolicies,
+    // base policy sets registered by inventories, keyed by inventory account
+    pub inventory_policies: LookupMap<AccountId, InventoryPolicies>,
 }
 
 #[near_bindgen]
@@ -30,6 +40,7 @@ impl Contract {
             //Storage keys are simply the prefixes used for the collections. This helps avoid data collision
             owner_id,
             policies,
+            inventory_policies: LookupMap::new(StorageKey::InventoryPolicies.try_to_vec().unwrap()),
         };
 
         this
@@ -1,8 +1,8 @@
 use near_sdk::{near_bindgen};
 use crate::*;
 use policy_rules::policy::{ConfigInterface, IsAvailableResponse, Limitation, Policy};
-use common_types::types::{FullInventory, InventoryLicense, ShrinkedLicenseToken};
-use policy_rules::types::{SKUAvailability};
+use common_types::types::{AssetLicense, FullInventory, InventoryLicense, ShrinkedLicenseToken};
+use policy_rules::types::{AssetChangeReport, SKUAvailability};
 
 #[near_bindgen]
 impl ConfigInterface for Contract {
@@ -10,13 +10,13 @@ impl ConfigInterface for Contract {
     fn check_transition(
         &self, inventory: FullInventory, old: ShrinkedLicenseToken,
         new: ShrinkedLicenseToken, policy_rules: Option<Vec<Limitation>>, upgrade_rules: Option<Vec<Policy>>) -> Result<IsAvailableResponse, String> {
-        self.policies.check_transition(inventory, old, new, policy_rules, upgrade_rules)
+        self.resolve_policies(inventory.inventory_id.as_ref()).check_transition(inventory, old, new, policy_rules, upgrade_rules)
     }
 
     fn check_new(
         &self, inventory: FullInventory, new: ShrinkedLicenseToken,
         policy_rules: Option<Vec<Limitation>>, upgrade_rules: Option<Vec<Policy>>) -> IsAvailableResponse {
-        self.policies.check_new(inventory, new, policy_rules, upgrade_rules)
+        self.resolve_policies(inventory.inventory_id.as_ref()).check_new(inventory, new, policy_rules, upgrade_rules)
     }
 
     fn check_state(&self, licenses: Vec<ShrinkedLicenseToken>) -> IsAvailableResponse {
@@ -24,18 +24,25 @@ impl ConfigInterface for Contract {
     }
 
     fn check_inventory_state(&self, licenses: Vec<InventoryLicense>) -> IsAvailableResponse {
-        self.policies.check_inventory_state(licenses)
+        // Called by the inventory contract itself, so resolve by the caller.
+        self.resolve_policies(Some(&env::predecessor_account_id())).check_inventory_state(licenses)
     }
 
     fn list_transitions(
         &self, inventory: FullInventory, from: ShrinkedLicenseToken,
         policy_rules: Option<Vec<Limitation>>, upgrade_rules: Option<Vec<Policy>>) -> Vec<SKUAvailability> {
-        self.policies.list_transitions(inventory, from, policy_rules, upgrade_rules)
+        self.resolve_policies(inventory.inventory_id.as_ref()).list_transitions(inventory, from, policy_rules, upgrade_rules)
     }
 
     fn list_available(
         &self, inventory: FullInventory, policy_rules: Option<Vec<Limitation>>,
         upgrade_rules: Option<Vec<Policy>>) -> Vec<SKUAvailability> {
-        self.policies.list_available(inventory, policy_rules, upgrade_rules)
+        self.resolve_policies(inventory.inventory_id.as_ref()).list_available(inventory, policy_rules, upgrade_rules)
     }
-}
\ No newline at end of file
+
+    fn simulate_asset_change(
+        &self, inventory: FullInventory, new_asset_licenses: Option<Vec<AssetLicense>>,
+        new_policy_rules: Option<Vec<Limitation>>) -> AssetChangeReport {
+        self.resolve_policies(inventory.inventory_id.as_ref()).simulate_asset_change(inventory, new_asset_licenses, new_policy_rules)
+    }
+}
@@ -0,0 +1,67 @@
+use near_sdk::{near_bindgen, Balance, Promise};
+use common_types::utils::refund_storage;
+use policy_rules::policy::{Limitation, Policy};
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    // Registers the base policy set of the calling inventory.
+    // Only the inventory contract itself may register its policies.
+    #[payable]
+    pub fn register_inventory_policies(
+        &mut self,
+        policy_rules: Option<Vec<Limitation>>,
+        upgrade_rules: Option<Vec<Policy>>,
+        predecessor_id: Option<AccountId>,
+    ) -> InventoryPolicies {
+        let initial_storage_usage = env::storage_usage();
+        let inventory_id = env::predecessor_account_id();
+
+        for policy in upgrade_rules.as_ref().unwrap_or(&Vec::new()) {
+            if policy.name.is_none() {
+                env::panic_str("Policy name is required")
+            }
+        }
+        let inventory_policies = InventoryPolicies{policy_rules, upgrade_rules};
+        let resolved = self.policies.clone_with_inventory(Some(inventory_policies.clone()));
+        if let Err(msg) = resolved.validate() {
+            env::panic_str(&msg)
+        }
+
+        self.inventory_policies.insert(&inventory_id, &inventory_policies);
+
+        if let Err(msg) = refund_storage(initial_storage_usage, predecessor_id, None) {
+            env::panic_str(&msg)
+        }
+        inventory_policies
+    }
+
+    // Removes the policy set of the calling inventory and refunds the released storage
+    // to predecessor_id, the inventory owner, or to the inventory itself if it is not set.
+    pub fn unregister_inventory_policies(&mut self, predecessor_id: Option<AccountId>) -> Option<InventoryPolicies> {
+        let initial_storage_usage = env::storage_usage();
+        let inventory_id = env::predecessor_account_id();
+        let removed = self.inventory_policies.remove(&inventory_id);
+
+        let storage_released = initial_storage_usage.saturating_sub(env::storage_usage());
+        let refund = Balance::from(storage_released) * env::storage_byte_cost();
+        if refund > 0 {
+            Promise::new(predecessor_id.unwrap_or(inventory_id)).transfer(refund);
+        }
+        removed
+    }
+
+    pub fn get_inventory_policies(&self, inventory_id: AccountId) -> Option<InventoryPolicies> {
+        self.inventory_policies.get(&inventory_id)
+    }
+
+    // Global policies merged with the ones registered by the inventory.
+    pub fn get_resolved_policies(&self, inventory_id: Option<AccountId>) -> AllPolicies {
+        self.resolve_policies(inventory_id.as_ref())
+    }
+
+    pub(crate) fn resolve_policies(&self, inventory_id: Option<&AccountId>) -> AllPolicies {
+        let inventory_policies = inventory_id.and_then(|x| self.inventory_policies.get(x));
+        self.policies.clone_with_inventory(inventory_policies)
+    }
+}
@@ -0,0 +1,271 @@
+use std::env;
+use std::fs;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::{serde_json, testing_env, AccountId};
+
+use common_types::types::{AssetLicense, FullInventory, InventoryLicense};
+use policy_rules::policy::{parse_policies, AllPolicies, ConfigInterface, InventoryPolicies, Limitation, Policy};
+use policy_rules::types::SKUAvailability;
+
+const USAGE: &str = "Usage: policy_simulator <rules.json> <inventory.json> <actions.json> [--inventory-policies <file>]
+
+  rules.json          global policies, same format as policy_rules/src/rules.json
+  inventory.json      FullInventory snapshot (inventory licenses, issued licenses, asset)
+  actions.json        list of actions to replay, e.g.
+                        [{\"mint\": {\"sku_id\": \"1\"}},
+                         {\"upgrade\": {\"token_id\": \"1\", \"new_sku_id\": \"2\"}}]
+  --inventory-policies  policies registered by the inventory (policy_rules / upgrade_rules)";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    Mint {
+        sku_id: String,
+        token_id: Option<String>,
+        owner_id: Option<AccountId>,
+    },
+    Upgrade {
+        token_id: String,
+        new_sku_id: String,
+    },
+}
+
+impl Action {
+    fn describe(&self) -> String {
+        match self {
+            Action::Mint { sku_id, .. } => format!("mint sku {}", sku_id),
+            Action::Upgrade { token_id, new_sku_id } => format!("upgrade token {} to sku {}", token_id, new_sku_id),
+        }
+    }
+}
+
+struct Simulator {
+    policies: AllPolicies,
+    inventory: FullInventory,
+    policy_rules: Option<Vec<Limitation>>,
+    upgrade_rules: Option<Vec<Policy>>,
+}
+
+impl Simulator {
+    fn new(policies: AllPolicies, inventory: FullInventory) -> Result<Self, String> {
+        let asset = inventory.asset.as_ref().ok_or("Inventory snapshot has no asset")?;
+        // Asset rules are stored as plain data, convert them the same way
+        // the policy contract receives them over JSON.
+        let policy_rules = convert(&asset.policy_rules)?;
+        let upgrade_rules = convert(&asset.upgrade_rules)?;
+
+        Ok(Simulator { policies, inventory, policy_rules, upgrade_rules })
+    }
+
+    fn apply(&mut self, action: &Action) -> Result<String, String> {
+        match action {
+            Action::Mint { sku_id, token_id, owner_id } => self.mint(sku_id, token_id.clone(), owner_id.clone()),
+            Action::Upgrade { token_id, new_sku_id } => self.upgrade(token_id, new_sku_id),
+        }
+    }
+
+    fn mint(&mut self, sku_id: &String, token_id: Option<String>, owner_id: Option<AccountId>) -> Result<String, String> {
+        let token_id = token_id.unwrap_or_else(|| self.next_token_id());
+        if self.inventory.issued_licenses.iter().any(|x| x.token_id == token_id) {
+            return Err(format!("Token {} already exists", token_id))
+        }
+        let (sku_info, inv_license) = self.find_sku(sku_id)?;
+
+        let asset = self.inventory.asset.as_ref().unwrap();
+        let mut token = asset.issue_new_license(inv_license, sku_info, token_id.clone());
+        if let Some(owner_id) = owner_id {
+            token.owner_id = owner_id;
+        }
+        let new = token.shrink();
+
+        let res = self.policies.check_new(
+            self.inventory.clone(), new.clone(), self.policy_rules.clone(), self.upgrade_rules.clone(),
+        );
+        if !res.result {
+            return Err(res.reason_not_available)
+        }
+        self.inventory.issued_licenses.push(new);
+        Ok(format!("issued token {} to {}", token_id, token.owner_id))
+    }
+
+    fn upgrade(&mut self, token_id: &String, new_sku_id: &String) -> Result<String, String> {
+        let pos = self.inventory.issued_licenses.iter().position(|x| &x.token_id == token_id)
+            .ok_or(format!("Token {} not found", token_id))?;
+        let old = self.inventory.issued_licenses[pos].clone();
+        let (sku_info, inv_license) = self.find_sku(new_sku_id)?;
+
+        let asset = self.inventory.asset.as_ref().unwrap();
+        let mut new = asset.issue_new_license(inv_license, sku_info, token_id.clone()).shrink();
+        new.owner_id = old.owner_id.clone();
+
+        let res = self.policies.check_transition(
+            self.inventory.clone(), old, new.clone(), self.policy_rules.clone(), self.upgrade_rules.clone(),
+        )?;
+        if !res.result {
+            return Err(res.reason_not_available)
+        }
+        self.inventory.issued_licenses[pos] = new;
+        Ok(format!("token {} now has sku {}", token_id, new_sku_id))
+    }
+
+    fn find_sku(&self, sku_id: &String) -> Result<(AssetLicense, Option<InventoryLicense>), String> {
+        let asset = self.inventory.asset.as_ref().unwrap();
+        let sku_info = asset.licenses.as_ref()
+            .and_then(|licenses| licenses.iter().find(|x| x.sku_id.as_ref() == Some(sku_id)))
+            .ok_or(format!("Asset license not found by sku_id {}", sku_id))?
+            .clone();
+        let license_id = sku_info.license_id.clone().unwrap_or_default();
+        let inv_license = self.inventory.inventory_licenses.iter()
+            .find(|x| x.license_id == license_id)
+            .cloned()
+            .ok_or(format!("Inventory license not found by license_id {}", license_id))?;
+
+        Ok((sku_info, Some(inv_license)))
+    }
+
+    // Same as nft_token_id_max: next numeric id after the highest issued one.
+    fn next_token_id(&self) -> String {
+        let max = self.inventory.issued_licenses.iter()
+            .filter_map(|x| x.token_id.parse::<u64>().ok())
+            .max()
+            .unwrap_or(0);
+        (max + 1).to_string()
+    }
+
+    fn print_available(&self) {
+        let available = self.policies.list_available(
+            self.inventory.clone(), self.policy_rules.clone(), self.upgrade_rules.clone(),
+        );
+        println!("  {:<36} {:<24} {:<10} {:>7} {:>8} {:>8}  REASON", "SKU", "TITLE", "AVAILABLE", "ISSUED", "REMAINS", "TOTAL");
+        for sku in available {
+            print_row(&sku);
+        }
+    }
+}
+
+fn print_row(sku: &SKUAvailability) {
+    // The tightest limit is the one that matters for the buyer.
+    let limit = sku.additional_info.as_ref()
+        .and_then(|info| info.values().min_by_key(|x| x.remains));
+    let (issued, remains, total) = match limit {
+        Some(l) => (l.issued.to_string(), l.remains.to_string(), l.total.to_string()),
+        None => ("-".to_string(), "-".to_string(), "-".to_string()),
+    };
+    println!(
+        "  {:<36} {:<24} {:<10} {:>7} {:>8} {:>8}  {}",
+        sku.asset_license.sku_id.clone().unwrap_or_default(),
+        sku.asset_license.title,
+        if sku.available { "yes" } else { "no" },
+        issued, remains, total,
+        sku.reason_not_available.clone().unwrap_or_default(),
+    );
+}
+
+fn convert<F: Serialize, T: DeserializeOwned>(data: &Option<Vec<F>>) -> Result<Option<Vec<T>>, String> {
+    match data {
+        Some(data) => {
+            let value = serde_json::to_value(data).map_err(|e| e.to_string())?;
+            serde_json::from_value(value).map(Some).map_err(|e| e.to_string())
+        }
+        None => Ok(None),
+    }
+}
+
+fn read_file(path: &String) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))
+}
+
+fn read_json<T: DeserializeOwned>(path: &String) -> Result<T, String> {
+    let raw = read_file(path)?;
+    serde_json::from_slice(&raw).map_err(|e| format!("Failed to parse {}: {}", path, e))
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut files: Vec<String> = Vec::new();
+    let mut inventory_policies: Option<InventoryPolicies> = None;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--inventory-policies" {
+            let path = iter.next().ok_or(USAGE)?;
+            inventory_policies = Some(read_json(&path)?);
+        } else if arg == "-h" || arg == "--help" {
+            println!("{}", USAGE);
+            return Ok(())
+        } else {
+            files.push(arg);
+        }
+    }
+    if files.len() != 3 {
+        return Err(USAGE.to_string())
+    }
+
+    // Templates and license timestamps read the block time, use the wall clock.
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?;
+    testing_env!(VMContextBuilder::new().block_timestamp(now.as_nanos() as u64).build());
+
+    let rules = parse_policies(&read_file(&files[0])?)
+        .map_err(|e| format!("Failed to parse {}: {}", files[0], e))?;
+    let policies = rules.clone_with_inventory(inventory_policies);
+    policies.validate()?;
+    let inventory: FullInventory = read_json(&files[1])?;
+    let actions: Vec<Action> = read_json(&files[2])?;
+
+    let mut sim = Simulator::new(policies, inventory)?;
+    println!("Initial state: {} issued licenses", sim.inventory.issued_licenses.len());
+    sim.print_available();
+
+    for (i, action) in actions.iter().enumerate() {
+        println!();
+        match sim.apply(action) {
+            Ok(msg) => println!("#{} {}: OK, {}", i + 1, action.describe(), msg),
+            Err(msg) => println!("#{} {}: REJECTED, {}", i + 1, action.describe(), msg),
+        }
+        sim.print_available();
+    }
+
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(msg) = run(args) {
+        eprintln!("{}", msg);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common_types::types::LicenseGeneral;
+
+    fn example_path(name: &str) -> String {
+        format!("{}/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn test_examples() {
+        testing_env!(VMContextBuilder::new().build());
+        let rules = parse_policies(&read_file(&example_path("../policy_rules/src/rules.json")).unwrap()).unwrap();
+        let inventory: FullInventory = read_json(&example_path("examples/inventory.json")).unwrap();
+        let actions: Vec<Action> = read_json(&example_path("examples/actions.json")).unwrap();
+
+        let mut sim = Simulator::new(rules, inventory).unwrap();
+        let results: Vec<Result<String, String>> = actions.iter().map(|x| sim.apply(x)).collect();
+        assert_eq!(results[0], Ok("token 1 now has sku c4ccc6c1-fe2c-4903-99d8-b8dee1418f7b".to_string()));
+        assert_eq!(results[1], Ok("issued token 2 to buyer.testnet".to_string()));
+        assert_eq!(
+            results[2],
+            Err("Count of exclusive for object ba1117f1-3951-46ed-836f-022c1b62d1f1 cannot be greater than 1".to_string()),
+        );
+        assert_eq!(sim.inventory.issued_licenses.len(), 2);
+        // the upgraded token now has the commercial license
+        assert!(!sim.inventory.issued_licenses[0].is_personal());
+    }
+}